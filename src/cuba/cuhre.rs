@@ -1,24 +1,29 @@
-use std::{mem, ptr};
+use std::ptr;
 use std::os::raw::{c_int, c_longlong};
 
 use ::bindings;
-use ::ffi::LandingPad;
 use ::traits::{IntegrandInput, IntegrandOutput};
-use ::{Integrator, Real};
+use ::{DynIntegrator, Integrator, Real};
 
-use super::{cuba_integrand, CubaError, CubaIntegrationResult, CubaIntegrationResults};
+use super::{integrate_dyn, integrate_scalar, integrate_vectorized, CubaError,
+            CubaIntegrationResults, CubaOutputs, CubaRoutine, CubaSpin, IntegrationRange,
+            RawIntegrand, ThreadPool,
+            spin_ptr};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Cuhre {
     pub mineval: usize,
     pub maxeval: usize,
     key: Option<u16>,
+    nvec: usize,
+    spin: Option<CubaSpin>,
+    ranges: Option<Vec<IntegrationRange>>,
 }
 
 impl Cuhre {
     pub fn new(maxeval: usize) -> Self {
         Cuhre {
-            mineval: 1, maxeval, key: None
+            mineval: 1, maxeval, key: None, nvec: 1, spin: None, ranges: None
         }
     }
 
@@ -34,6 +39,14 @@ impl Cuhre {
         }
     }
 
+    /// Set the maximum number of points passed to the integrand at once by
+    /// `integrate_vectorized`. (Default = 1)
+    pub fn with_nvec(self, nvec: usize) -> Self {
+        Cuhre {
+            nvec, ..self
+        }
+    }
+
     pub fn with_key(self, key: Option<u16>) -> Option<Self> {
         if key.map(|k| [7, 9, 11, 13].contains(&k)).unwrap_or(true) {
             None
@@ -43,32 +56,72 @@ impl Cuhre {
             })
         }
     }
-}
 
-impl Integrator for Cuhre {
-    type Success = CubaIntegrationResults;
-    type Failure = super::CubaError;
-    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+    /// Keep Cuba's worker processes alive between integrations, using the
+    /// given handle. See `CubaSpin`.
+    pub fn with_spin(self, spin: CubaSpin) -> Self {
+        Cuhre {
+            spin: Some(spin), ..self
+        }
+    }
+
+    /// Removes the worker handle, if any, from the integrator.
+    pub fn take_spin(&mut self) -> Option<CubaSpin> {
+        self.spin.take()
+    }
+
+    /// Integrate over the given range of each dimension, rather than the
+    /// unit hypercube. The integrand is given the transformed coordinates,
+    /// and the results include the Jacobian of the transformation. There
+    /// must be one range for each dimension of the integrand, or else
+    /// integration fails with `CubaError::BadRangeDim`.
+    pub fn with_ranges(self, ranges: Vec<IntegrationRange>) -> Self {
+        Cuhre {
+            ranges: Some(ranges), ..self
+        }
+    }
+
+    /// Integrate over the unit hypercube again.
+    pub fn without_ranges(self) -> Self {
+        Cuhre {
+            ranges: None, ..self
+        }
+    }
+
+    /// The range of each dimension, if given.
+    pub fn ranges(&self) -> Option<&[IntegrationRange]> {
+        self.ranges.as_ref().map(|ranges| &ranges[..])
+    }
+
+    /// Integrates a vectorized integrand, which is given a slice of up to
+    /// `nvec` points at a time and must return one output for each. See the
+    /// `cuba` module documentation for details.
+    pub fn integrate_vectorized<A, B, F>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
         where A: IntegrandInput,
-              B: IntegrandOutput
+              B: IntegrandOutput,
+              F: FnMut(&[A]) -> Vec<B>
     {
-        // Using cuba's parallelization via fork() would deeply break Rust's
-        // concurrency model and safety guarantees. So, we'll turn it off.
-        unsafe { bindings::cubacores(0, 0) };
-
-        let (ndim, ncomp) = {
-            let inputs = A::input_size();
-            let outputs = fun(A::from_args(&vec![0.5; inputs][..])).output_size();
-            (inputs, outputs)
-        };
+        let nvec = self.nvec;
+        integrate_vectorized(self, nvec, fun, epsrel, epsabs)
+    }
 
-        let mut nregions = 0;
-        let mut neval = 0;
-        let mut fail = 0;
-        let (mut value, mut error, mut prob) =
-                (vec![0.0; ncomp], vec![0.0; ncomp], vec![0.0; ncomp]);
+    /// Integrates `fun` in parallel, by splitting each batch of up to `nvec`
+    /// points across the threads of `pool`. `nvec` should be set well above
+    /// the number of threads for this to be worthwhile. See `ThreadPool`.
+    pub fn integrate_parallel<A, B, F>(&mut self, pool: &ThreadPool, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput + Clone + Sync,
+              B: IntegrandOutput + Send,
+              F: Fn(A) -> B + Sync
+    {
+        self.integrate_vectorized(|points: &[A]| pool.map(&fun, points),
+                                  epsrel, epsabs)
+    }
+}
 
-        let key = match (self.key, ndim) {
+impl CubaRoutine for Cuhre {
+    unsafe fn call(&mut self, raw: &RawIntegrand, epsrel: Real, epsabs: Real)
+            -> Result<CubaIntegrationResults, CubaError> {
+        let key = match (self.key, raw.ndim) {
             (Some(key), _) => key,
             (_, 1) | (_, 2) => 13,
             (_, 3) => 11,
@@ -77,58 +130,53 @@ impl Integrator for Cuhre {
 
         assert!([7, 9, 11, 13].contains(&key));
 
-        let mut lp = LandingPad::new(fun);
-        unsafe {
-            bindings::llCuhre(ndim as c_int, ncomp as c_int,
-                              Some(cuba_integrand::<A, B, F>), mem::transmute(&mut lp),
-                              1 /* nvec */,
-                              epsrel,
-                              epsabs,
-                              0 /* flags */,
-                              self.mineval as c_longlong,
-                              self.maxeval as c_longlong,
-                              key as c_int,
-                              // statefile
-                              ptr::null(),
-                              // spin
-                              ptr::null_mut(),
-                              &mut nregions,
-                              &mut neval,
-                              &mut fail,
-                              value.as_mut_ptr(),
-                              error.as_mut_ptr(),
-                              prob.as_mut_ptr());
-        }
-        lp.maybe_resume_unwind();
-
-        if fail == 0 {
-            Ok(CubaIntegrationResults {
-                nregions: Some(nregions), neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            })
-        } else if fail == -1 {
-            // `baddim`
-            Err(CubaError::BadDim("cuhre", ndim))
-        } else if fail == -2 {
-            // `badcomp`
-            Err(CubaError::BadComp("cuhre", ncomp))
-        } else if fail == 1 {
-            Err(CubaError::DidNotConverge(CubaIntegrationResults {
-                nregions: Some(nregions), neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            }))
-        } else {
-            unreachable!("panic should have been propogated into Rust caller")
-        }
+        let mut nregions = 0;
+        let mut out = CubaOutputs::new(raw.ncomp);
+        bindings::llCuhre(raw.ndim as c_int, raw.ncomp as c_int,
+                          raw.integrand, raw.userdata,
+                          raw.nvec as c_longlong,
+                          epsrel,
+                          epsabs,
+                          0 /* flags */,
+                          self.mineval as c_longlong,
+                          self.maxeval as c_longlong,
+                          key as c_int,
+                          // statefile
+                          ptr::null(),
+                          spin_ptr(&mut self.spin),
+                          &mut nregions,
+                          &mut out.neval,
+                          &mut out.fail,
+                          out.value.as_mut_ptr(),
+                          out.error.as_mut_ptr(),
+                          out.prob.as_mut_ptr());
+        out.nregions = Some(nregions);
+        out.into_results("cuhre", raw.ndim, raw.ncomp)
+    }
+
+    fn ranges(&self) -> Option<&[IntegrationRange]> {
+        Cuhre::ranges(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "cuhre"
+    }
+}
+
+impl Integrator for Cuhre {
+    type Success = CubaIntegrationResults;
+    type Failure = CubaError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        integrate_scalar(self, fun, epsrel, epsabs)
+    }
+}
+
+impl DynIntegrator for Cuhre {
+    fn integrate_dyn(&mut self, ndim: usize, ncomp: usize, fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                     epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure> {
+        integrate_dyn(self, ndim, ncomp, fun, epsrel, epsabs)
     }
 }
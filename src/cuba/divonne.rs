@@ -0,0 +1,342 @@
+use std::ptr;
+use std::os::raw::{c_int, c_longlong};
+
+use ::bindings;
+use ::traits::{IntegrandInput, IntegrandOutput};
+use ::{DynIntegrator, Integrator, Real};
+
+use super::{integrate_dyn, integrate_scalar, integrate_vectorized, CubaError,
+            CubaIntegrationResults, CubaOutputs, CubaRoutine, CubaSpin, IntegrationRange,
+            RawIntegrand, ThreadPool,
+            spin_ptr,
+            RandomNumberSource};
+
+/// Cuba's Divonne algorithm. Divonne partitions the integration region into
+/// subregions of (roughly) equal integrand spread using numerical
+/// minimization, then samples each subregion with a cubature rule or
+/// quasi-random points. It is especially good at integrands with sharp peaks
+/// or ridges, particularly if their locations are known ahead of time, in
+/// which case they can be given with `with_xgiven`.
+///
+/// Divonne only supports integrands of at least two dimensions.
+///
+/// See Cuba's documentation for a full description of each parameter.
+#[derive(Clone, Debug)]
+pub struct Divonne {
+    mineval: usize,
+    maxeval: usize,
+    seed: usize,
+    key1: c_int,
+    key2: c_int,
+    key3: c_int,
+    maxpass: usize,
+    border: Real,
+    maxchisq: Real,
+    mindeviation: Real,
+    xgiven: Vec<Vec<Real>>,
+    nvec: usize,
+    spin: Option<CubaSpin>,
+    ranges: Option<Vec<IntegrationRange>>,
+    flags: c_int,
+}
+
+impl Default for Divonne {
+    fn default() -> Self {
+        Divonne {
+            mineval: 1,
+            maxeval: c_longlong::max_value() as usize,
+            seed: 0,
+            key1: 47,
+            key2: 1,
+            key3: 1,
+            maxpass: 5,
+            border: 0.0,
+            maxchisq: 10.0,
+            mindeviation: 0.25,
+            xgiven: Vec::new(),
+            nvec: 1,
+            spin: None,
+            ranges: None,
+            flags: 0,
+        }
+    }
+}
+
+fn verify_given_points<I>(iter: I) -> Option<Vec<Vec<Real>>>
+    where I: IntoIterator<Item=Vec<Real>> {
+    let points = iter.into_iter().collect::<Vec<Vec<Real>>>();
+
+    if let Some(first) = points.first() {
+        let ndim = first.len();
+        for point in points.iter() {
+            if point.len() != ndim {
+                return None
+            }
+            if point.iter().any(|&x| !((x >= 0.0) & (x <= 1.0))) {
+                return None
+            }
+        }
+    }
+    Some(points)
+}
+
+impl Divonne {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_mineval(self, mineval: usize) -> Self {
+        Divonne {
+            mineval, ..self
+        }
+    }
+
+    pub fn with_maxeval(self, maxeval: usize) -> Self {
+        Divonne {
+            maxeval, ..self
+        }
+    }
+
+    /// Set the random number generator seed.
+    pub fn with_seed(self, seed: usize) -> Self {
+        Divonne {
+            seed, ..self
+        }
+    }
+
+    /// Set the sampling rule used in the partitioning phase. Values of 7, 9,
+    /// 11 or 13 select a cubature rule of that degree, while any other value
+    /// uses that many quasi-random points. (Default = 47)
+    pub fn with_key1(self, key1: c_int) -> Self {
+        Divonne {
+            key1, ..self
+        }
+    }
+
+    /// Set the sampling rule used in the final integration phase. It has
+    /// the same meaning as `key1`. (Default = 1)
+    pub fn with_key2(self, key2: c_int) -> Self {
+        Divonne {
+            key2, ..self
+        }
+    }
+
+    /// Set the strategy for the refinement phase. `0` does no further
+    /// treatment, `1` splits subregions further, and any other value samples
+    /// subregions again with that rule. (Default = 1)
+    pub fn with_key3(self, key3: c_int) -> Self {
+        Divonne {
+            key3, ..self
+        }
+    }
+
+    /// Set the number of passes after which the partitioning phase
+    /// terminates, if no improvement is made. (Default = 5)
+    pub fn with_maxpass(self, maxpass: usize) -> Self {
+        Divonne {
+            maxpass, ..self
+        }
+    }
+
+    /// Set the width of the border of the integration region, which will not
+    /// be sampled directly but extrapolated from the interior. (Default = 0)
+    pub fn with_border(self, border: Real) -> Self {
+        Divonne {
+            border, ..self
+        }
+    }
+
+    /// Set the maximum chi-squared a subregion may fail the consistency test
+    /// with before being re-sampled. (Default = 10)
+    pub fn with_maxchisq(self, maxchisq: Real) -> Self {
+        Divonne {
+            maxchisq, ..self
+        }
+    }
+
+    /// Set the minimum fraction of the requested error which a subregion
+    /// failing the chi-squared test must contribute to be re-sampled.
+    /// (Default = 0.25)
+    pub fn with_mindeviation(self, mindeviation: Real) -> Self {
+        Divonne {
+            mindeviation, ..self
+        }
+    }
+
+    /// Provides the locations of known peaks of the integrand, in the unit
+    /// hypercube. Every point must have the same number of dimensions as
+    /// the integrand's input.
+    /// Returns `None` if the points do not all have the same length, or if
+    /// any coordinate lies outside [0, 1].
+    pub fn with_xgiven<I>(self, iter: I) -> Option<Self>
+        where I: IntoIterator<Item=Vec<Real>> {
+        Some(Divonne {
+            xgiven: verify_given_points(iter)?,
+            ..self
+        })
+    }
+
+    pub fn xgiven(&self) -> &[Vec<Real>] {
+        &self.xgiven[..]
+    }
+
+    /// Set the maximum number of points passed to the integrand at once by
+    /// `integrate_vectorized`. (Default = 1)
+    pub fn with_nvec(self, nvec: usize) -> Self {
+        Divonne {
+            nvec, ..self
+        }
+    }
+
+    /// Set the random number generator source.
+    pub fn with_rng(self, rng: RandomNumberSource) -> Self {
+        Divonne {
+            flags: (self.flags & !0x8) | match rng {
+                RandomNumberSource::Sobol => 0,
+                RandomNumberSource::MersenneTwister => 8,
+            }, ..self
+        }
+    }
+
+    /// Keep Cuba's worker processes alive between integrations, using the
+    /// given handle. See `CubaSpin`.
+    pub fn with_spin(self, spin: CubaSpin) -> Self {
+        Divonne {
+            spin: Some(spin), ..self
+        }
+    }
+
+    /// Removes the worker handle, if any, from the integrator.
+    pub fn take_spin(&mut self) -> Option<CubaSpin> {
+        self.spin.take()
+    }
+
+    /// Integrate over the given range of each dimension, rather than the
+    /// unit hypercube. The integrand is given the transformed coordinates,
+    /// and the results include the Jacobian of the transformation. There
+    /// must be one range for each dimension of the integrand, or else
+    /// integration fails with `CubaError::BadRangeDim`.
+    ///
+    /// The points given with `with_xgiven` are still in the unit hypercube.
+    pub fn with_ranges(self, ranges: Vec<IntegrationRange>) -> Self {
+        Divonne {
+            ranges: Some(ranges), ..self
+        }
+    }
+
+    /// Integrate over the unit hypercube again.
+    pub fn without_ranges(self) -> Self {
+        Divonne {
+            ranges: None, ..self
+        }
+    }
+
+    /// The range of each dimension, if given.
+    pub fn ranges(&self) -> Option<&[IntegrationRange]> {
+        self.ranges.as_ref().map(|ranges| &ranges[..])
+    }
+
+    /// Integrates a vectorized integrand, which is given a slice of up to
+    /// `nvec` points at a time and must return one output for each. See the
+    /// `cuba` module documentation for details.
+    pub fn integrate_vectorized<A, B, F>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput,
+              B: IntegrandOutput,
+              F: FnMut(&[A]) -> Vec<B>
+    {
+        let nvec = self.nvec;
+        integrate_vectorized(self, nvec, fun, epsrel, epsabs)
+    }
+
+    /// Integrates `fun` in parallel, by splitting each batch of up to `nvec`
+    /// points across the threads of `pool`. `nvec` should be set well above
+    /// the number of threads for this to be worthwhile. See `ThreadPool`.
+    pub fn integrate_parallel<A, B, F>(&mut self, pool: &ThreadPool, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput + Clone + Sync,
+              B: IntegrandOutput + Send,
+              F: Fn(A) -> B + Sync
+    {
+        self.integrate_vectorized(|points: &[A]| pool.map(&fun, points),
+                                  epsrel, epsabs)
+    }
+}
+
+impl CubaRoutine for Divonne {
+    unsafe fn call(&mut self, raw: &RawIntegrand, epsrel: Real, epsabs: Real)
+            -> Result<CubaIntegrationResults, CubaError> {
+        // Cuba expects the given points as a flat array, with a stride of
+        // `ldxgiven` between consecutive points.
+        let mut xgiven = Vec::with_capacity(self.xgiven.len() * raw.ndim);
+        for point in self.xgiven.iter() {
+            if point.len() != raw.ndim {
+                return Err(CubaError::BadGivenDim(point.len(), raw.ndim));
+            }
+            xgiven.extend_from_slice(&point[..]);
+        }
+
+        let mut nregions = 0;
+        let mut out = CubaOutputs::new(raw.ncomp);
+        bindings::llDivonne(raw.ndim as c_int, raw.ncomp as c_int,
+                            raw.integrand, raw.userdata,
+                            raw.nvec as c_longlong,
+                            epsrel,
+                            epsabs,
+                            self.flags,
+                            self.seed as c_int,
+                            self.mineval as c_longlong,
+                            self.maxeval as c_longlong,
+                            self.key1,
+                            self.key2,
+                            self.key3,
+                            self.maxpass as c_int,
+                            self.border,
+                            self.maxchisq,
+                            self.mindeviation,
+                            self.xgiven.len() as c_longlong,
+                            raw.ndim as c_int /* ldxgiven */,
+                            if xgiven.is_empty() {
+                                ptr::null_mut()
+                            } else {
+                                xgiven.as_mut_ptr()
+                            },
+                            0 /* nextra */,
+                            None /* peakfinder */,
+                            // statefile
+                            ptr::null(),
+                            spin_ptr(&mut self.spin),
+                            &mut nregions,
+                            &mut out.neval,
+                            &mut out.fail,
+                            out.value.as_mut_ptr(),
+                            out.error.as_mut_ptr(),
+                            out.prob.as_mut_ptr());
+        out.nregions = Some(nregions);
+        out.into_results("divonne", raw.ndim, raw.ncomp)
+    }
+
+    fn ranges(&self) -> Option<&[IntegrationRange]> {
+        Divonne::ranges(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "divonne"
+    }
+}
+
+impl Integrator for Divonne {
+    type Success = CubaIntegrationResults;
+    type Failure = CubaError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        integrate_scalar(self, fun, epsrel, epsabs)
+    }
+}
+
+impl DynIntegrator for Divonne {
+    fn integrate_dyn(&mut self, ndim: usize, ncomp: usize, fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                     epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure> {
+        integrate_dyn(self, ndim, ncomp, fun, epsrel, epsabs)
+    }
+}
@@ -53,6 +53,9 @@
 //! }
 //! ```
 //!
+//! The `domain` module does such changes of variables for common regions,
+//! such as `domain::Ball`.
+//!
 //! # Vectorized Integration
 //!
 //! You can integrate an arbitrary number of functions at the same time, by
@@ -109,23 +112,65 @@
 //!     assert!((calc - ex).abs() < ex*1e-5);
 //! }
 //! ```
+//!
+//! # Batched Evaluation
+//!
+//! By default, Cuba calls back into Rust once for every sample point. For
+//! integrands which benefit from evaluating many points at once (using SIMD,
+//! BLAS, or simply to amortize some set-up cost), each integrator has an
+//! `integrate_vectorized` method, which takes an integrand mapping a slice of
+//! points to a vector of outputs, one per point. The maximum number of points
+//! passed at a time is set with `with_nvec`.
+//!
+//! ```
+//! use integrators::{Real, Real2};
+//! use integrators::cuba::Vegas;
+//!
+//! let res = Vegas::new().with_maxeval(1000000)
+//!                       .with_nvec(1000)
+//!                       .integrate_vectorized(|points: &[Real2]| {
+//!                           points.iter()
+//!                                 .map(|&(x, y)| x * y)
+//!                                 .collect::<Vec<Real>>()
+//!                       }, 1e-3, 1e-12).unwrap();
+//!
+//! assert!((res.results[0].value - 0.25).abs() < 1e-3);
+//! ```
+//!
+//! Batched evaluation also allows integrands to be evaluated on multiple
+//! threads: `integrate_parallel` takes a `Sync` integrand and a `ThreadPool`,
+//! and splits each batch across the pool's threads.
+//!
+//! When the number of dimensions or outputs is only known at runtime, every
+//! integrator implements `DynIntegrator`, whose `integrate_dyn` takes an
+//! integrand which reads its inputs from a slice and writes its outputs to
+//! another.
 
-use std::{error, fmt, slice, vec};
+use std::{cmp, error, fmt, mem, ptr, slice, vec};
 use std::convert::From;
 use std::os::raw::{c_int, c_longlong, c_void};
 
 use super::traits::{IntegrandInput, IntegrandOutput};
-use super::{IntegrationResult, Real};
+use super::{bindings, IntegrationResult, Real};
 use super::ffi::LandingPad;
 
 mod cuhre;
 pub use self::cuhre::Cuhre;
 
+mod divonne;
+pub use self::divonne::Divonne;
+
 mod suave;
 pub use self::suave::Suave;
 
 mod vegas;
-pub use self::vegas::Vegas;
+pub use self::vegas::{Vegas, StateFileError, MAX_GRIDNO};
+
+mod parallel;
+pub use self::parallel::ThreadPool;
+
+mod spin;
+pub use self::spin::CubaSpin;
 
 unsafe extern "C"
 fn cuba_integrand<A, B, F>(ndim: *const c_int,
@@ -150,9 +195,368 @@ fn cuba_integrand<A, B, F>(ndim: *const c_int,
     }
 }
 
+unsafe extern "C"
+fn cuba_dyn_integrand<F>(ndim: *const c_int,
+                         x: *const Real,
+                         ncomp: *const c_int,
+                         f: *mut Real,
+                         userdata: *mut c_void) -> c_int
+    where F: FnMut(&[Real], &mut [Real])
+{
+    let lp = &mut *(userdata as *mut LandingPad<(), (), F>);
+
+    let args = slice::from_raw_parts(x, *ndim as usize);
+    let output = slice::from_raw_parts_mut(f, *ncomp as usize);
+
+    match lp.try_call_dyn(args, output) {
+        Ok(_) => 0,
+        // -999 is special `abort` code to Cuba
+        Err(_) => -999,
+    }
+}
+
+/// Cuba calls integrands with two more arguments than `integrand_t`
+/// declares: the number of points in the current batch, and the index of the
+/// calling core. Only the first is needed here.
+unsafe extern "C"
+fn cuba_vectorized_integrand<A, B, F>(ndim: *const c_int,
+                                      x: *const Real,
+                                      ncomp: *const c_int,
+                                      f: *mut Real,
+                                      userdata: *mut c_void,
+                                      nvec: *const c_int) -> c_int
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(&[A]) -> Vec<B>
+{
+    let fnptr = userdata as *mut LandingPad<A, B, F>;
+    let lp: &mut LandingPad<A, B, F> = &mut *fnptr;
+
+    let npoints = *nvec as usize;
+    let args = slice::from_raw_parts(x, (*ndim as usize) * npoints);
+    let output = slice::from_raw_parts_mut(f, (*ncomp as usize) * npoints);
+
+    match lp.try_call_vectorized(args, output) {
+        Ok(_) => 0,
+        // -999 is special `abort` code to Cuba
+        Err(_) => -999,
+    }
+}
+
+type VectorizedIntegrandFn = unsafe extern "C" fn(*const c_int, *const Real,
+                                                  *const c_int, *mut Real,
+                                                  *mut c_void, *const c_int) -> c_int;
+
+fn vectorized_integrand_t<A, B, F>() -> bindings::integrand_t
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(&[A]) -> Vec<B>
+{
+    let fun: VectorizedIntegrandFn = cuba_vectorized_integrand::<A, B, F>;
+    // The extra argument is passed by Cuba regardless of the declared type,
+    // so this is sound under the C calling convention.
+    Some(unsafe { mem::transmute::<VectorizedIntegrandFn, _>(fun) })
+}
+
+/// A type-erased integrand, as it is handed to Cuba.
+struct RawIntegrand {
+    ndim: usize,
+    ncomp: usize,
+    nvec: usize,
+    integrand: bindings::integrand_t,
+    userdata: *mut c_void,
+}
+
+/// The outputs of a call to one of Cuba's routines.
+struct CubaOutputs {
+    nregions: Option<c_int>,
+    neval: c_longlong,
+    fail: c_int,
+    value: Vec<Real>,
+    error: Vec<Real>,
+    prob: Vec<Real>,
+}
+
+impl CubaOutputs {
+    fn new(ncomp: usize) -> Self {
+        CubaOutputs {
+            nregions: None,
+            neval: 0,
+            fail: 0,
+            value: vec![0.0; ncomp],
+            error: vec![0.0; ncomp],
+            prob: vec![0.0; ncomp],
+        }
+    }
+
+    fn into_results(self, name: &'static str, ndim: usize, ncomp: usize)
+            -> Result<CubaIntegrationResults, CubaError> {
+        let results = CubaIntegrationResults {
+            nregions: self.nregions,
+            neval: self.neval,
+            results: self.value.iter().zip(self.error.iter()).zip(self.prob.iter())
+                         .map(|((&value, &error), &prob)|
+                                CubaIntegrationResult {
+                                    value, error, prob
+                                })
+                         .collect()
+        };
+
+        if self.fail == 0 {
+            Ok(results)
+        } else if self.fail == -1 {
+            // `baddim`
+            Err(CubaError::BadDim(name, ndim))
+        } else if self.fail == -2 {
+            // `badcomp`
+            Err(CubaError::BadComp(name, ncomp))
+        } else if self.fail > 0 {
+            // Divonne reports the number of extra evaluations it estimates it
+            // would need to reach the requested accuracy, the others just 1.
+            Err(CubaError::DidNotConverge(results))
+        } else {
+            unreachable!("{} returned invalid failure code: {}", name, self.fail)
+        }
+    }
+}
+
+/// The `spin` argument to pass to Cuba's routines. Without a handle, Cuba
+/// starts and stops any workers within each call.
+pub(crate) fn spin_ptr(spin: &mut Option<CubaSpin>) -> *mut c_void {
+    spin.as_mut()
+        .map(CubaSpin::as_mut_ptr)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// The interface shared by each of Cuba's algorithms.
+trait CubaRoutine {
+    /// Calls the Cuba routine. `raw.userdata` must be valid for whatever
+    /// `raw.integrand` expects it to be.
+    unsafe fn call(&mut self, raw: &RawIntegrand, epsrel: Real, epsabs: Real)
+        -> Result<CubaIntegrationResults, CubaError>;
+
+    /// The range of each dimension to integrate over, if not the unit
+    /// hypercube.
+    fn ranges(&self) -> Option<&[IntegrationRange]>;
+
+    /// The name of the routine, as reported in errors.
+    fn name(&self) -> &'static str;
+}
+
+/// An integrand whose points are mapped from the unit hypercube to `ranges`,
+/// and whose outputs are scaled by the Jacobian of that map, before being
+/// handed to `raw`.
+struct RangeMap<'a> {
+    raw: &'a RawIntegrand,
+    ranges: &'a [IntegrationRange],
+    jacobian: Real,
+    points: Vec<Real>,
+}
+
+unsafe extern "C"
+fn range_mapped_integrand(ndim: *const c_int,
+                          x: *const Real,
+                          ncomp: *const c_int,
+                          f: *mut Real,
+                          userdata: *mut c_void,
+                          nvec: *const c_int) -> c_int {
+    let map = &mut *(userdata as *mut RangeMap);
+
+    let npoints = *nvec as usize;
+    let args = slice::from_raw_parts(x, (*ndim as usize) * npoints);
+    map.points.clear();
+    for point in args.chunks(*ndim as usize) {
+        map.points.extend(point.iter().zip(map.ranges.iter())
+                               .map(|(&x, range)| range.transform(x)));
+    }
+
+    // Every integrand is called with `nvec`, whether it uses it or not; see
+    // `vectorized_integrand_t`.
+    let integrand: VectorizedIntegrandFn = mem::transmute(
+        map.raw.integrand.expect("integrands are always given"));
+    let status = integrand(ndim, map.points.as_ptr(), ncomp, f, map.raw.userdata, nvec);
+    if status == 0 {
+        for f in slice::from_raw_parts_mut(f, (*ncomp as usize) * npoints) {
+            *f *= map.jacobian;
+        }
+    }
+    status
+}
+
+/// Calls `routine` on `raw`, over `ranges` if given.
+unsafe fn call_routine<C>(routine: &mut C, raw: &RawIntegrand,
+                          ranges: Option<&[IntegrationRange]>,
+                          epsrel: Real, epsabs: Real)
+        -> Result<CubaIntegrationResults, CubaError>
+    where C: CubaRoutine
+{
+    match ranges {
+        None => routine.call(raw, epsrel, epsabs),
+        Some(ranges) => {
+            let mut map = RangeMap {
+                raw, ranges,
+                jacobian: ranges.iter().map(IntegrationRange::jacobian).product(),
+                points: Vec::with_capacity(raw.ndim * raw.nvec),
+            };
+            let range_fn: VectorizedIntegrandFn = range_mapped_integrand;
+            routine.call(&RawIntegrand {
+                             ndim: raw.ndim,
+                             ncomp: raw.ncomp,
+                             nvec: raw.nvec,
+                             integrand: Some(mem::transmute::<VectorizedIntegrandFn, _>(range_fn)),
+                             userdata: &mut map as *mut RangeMap as *mut c_void,
+                         },
+                         epsrel, epsabs)
+        },
+    }
+}
+
+/// The point at which to first evaluate an integrand of `ndim` dimensions,
+/// to find its number of outputs: the center of the integration region.
+/// Returns `CubaError::BadRangeDim` if there are ranges for a different
+/// number of dimensions.
+fn probe_point(ranges: Option<&[IntegrationRange]>, ndim: usize) -> Result<Vec<Real>, CubaError> {
+    match ranges {
+        None => Ok(vec![0.5; ndim]),
+        Some(ranges) if ranges.len() == ndim => {
+            Ok(ranges.iter().map(|range| range.transform(0.5)).collect())
+        },
+        Some(ranges) => Err(CubaError::BadRangeDim(ranges.len(), ndim)),
+    }
+}
+
+fn integrate_scalar<C, A, B, F>(routine: &mut C, mut fun: F, epsrel: Real, epsabs: Real)
+        -> Result<CubaIntegrationResults, CubaError>
+    where C: CubaRoutine,
+          A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    // Using cuba's parallelization via fork() would deeply break Rust's
+    // concurrency model and safety guarantees. So, we'll turn it off.
+    unsafe { bindings::cubacores(0, 0) };
+
+    let ranges = routine.ranges().map(|ranges| ranges.to_vec());
+    let ranges = ranges.as_ref().map(|ranges| &ranges[..]);
+    let (ndim, ncomp) = {
+        let inputs = A::input_size();
+        let outputs = fun(A::from_args(&probe_point(ranges, inputs)?[..])).output_size();
+        (inputs, outputs)
+    };
+
+    let mut lp = LandingPad::new(fun);
+    let res = unsafe {
+        call_routine(routine,
+                     &RawIntegrand {
+                         ndim, ncomp, nvec: 1,
+                         integrand: Some(cuba_integrand::<A, B, F>),
+                         userdata: mem::transmute(&mut lp),
+                     },
+                     ranges, epsrel, epsabs)
+    };
+    lp.maybe_resume_unwind();
+    res
+}
+
+/// Integrates `fun`, whose `ndim` inputs and `ncomp` outputs are only known
+/// at runtime. See `DynIntegrator`.
+fn integrate_dyn<C, F>(routine: &mut C, ndim: usize, ncomp: usize, fun: F,
+                       epsrel: Real, epsabs: Real)
+        -> Result<CubaIntegrationResults, CubaError>
+    where C: CubaRoutine,
+          F: FnMut(&[Real], &mut [Real])
+{
+    // Using cuba's parallelization via fork() would deeply break Rust's
+    // concurrency model and safety guarantees. So, we'll turn it off.
+    unsafe { bindings::cubacores(0, 0) };
+
+    let ranges = routine.ranges().map(|ranges| ranges.to_vec());
+    let ranges = ranges.as_ref().map(|ranges| &ranges[..]);
+    probe_point(ranges, ndim)?;
+
+    let mut lp = LandingPad::new_dyn(fun);
+    let res = unsafe {
+        call_routine(routine,
+                     &RawIntegrand {
+                         ndim, ncomp, nvec: 1,
+                         integrand: Some(cuba_dyn_integrand::<F>),
+                         userdata: &mut lp as *mut LandingPad<(), (), F> as *mut c_void,
+                     },
+                     ranges, epsrel, epsabs)
+    };
+    lp.maybe_resume_unwind();
+    res
+}
+
+fn integrate_vectorized<C, A, B, F>(routine: &mut C, nvec: usize, fun: F, epsrel: Real, epsabs: Real)
+        -> Result<CubaIntegrationResults, CubaError>
+    where C: CubaRoutine,
+          A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(&[A]) -> Vec<B>
+{
+    // Using cuba's parallelization via fork() would deeply break Rust's
+    // concurrency model and safety guarantees. So, we'll turn it off.
+    unsafe { bindings::cubacores(0, 0) };
+
+    let ranges = routine.ranges().map(|ranges| ranges.to_vec());
+    let ranges = ranges.as_ref().map(|ranges| &ranges[..]);
+    // Points are split into chunks of `ndim` values, and outputs into
+    // chunks of `ncomp`, so neither may be 0.
+    let inputs = A::input_size();
+    if inputs == 0 {
+        return Err(CubaError::BadDim(routine.name(), inputs));
+    }
+    let mut lp = LandingPad::new_vectorized(fun);
+    let (ndim, ncomp) = {
+        let outputs = lp.raw_call_vectorized(&probe_point(ranges, inputs)?[..]);
+        if outputs.len() != 1 {
+            panic!("Vectorized integrand returned {} outputs for {} points",
+                   outputs.len(), 1);
+        }
+        (inputs, outputs[0].output_size())
+    };
+    if ncomp == 0 {
+        return Err(CubaError::BadComp(routine.name(), ncomp));
+    }
+
+    let res = unsafe {
+        call_routine(routine,
+                     &RawIntegrand {
+                         ndim, ncomp, nvec: cmp::max(nvec, 1),
+                         integrand: vectorized_integrand_t::<A, B, F>(),
+                         userdata: mem::transmute(&mut lp),
+                     },
+                     ranges, epsrel, epsabs)
+    };
+    lp.maybe_resume_unwind();
+    res
+}
+
 /// Since Cuba integrates on the unit hypercube, it is convenient to have a
 /// helper to convert into a different integration range.
-#[derive(Debug, PartialEq)]
+///
+/// Each integrator can also be given one range per dimension with
+/// `with_ranges`, in which case it applies the transformation and Jacobian
+/// itself, and the integrand receives the transformed coordinates:
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use integrators::{Integrator, Real3};
+/// use integrators::cuba::{Cuhre, IntegrationRange};
+///
+/// // The volume of a sphere of radius 2, in spherical coordinates
+/// let res = Cuhre::new(999999)
+///                 .with_ranges(vec![IntegrationRange::new(0.0, 2.0),
+///                                   IntegrationRange::new(0.0, PI),
+///                                   IntegrationRange::new(0.0, 2.0 * PI)])
+///                 .integrate(|(r, theta, _phi): Real3| r * r * theta.sin(),
+///                            1e-5, 1e-18)
+///                 .unwrap();
+/// let exact = 4.0 / 3.0 * PI * 8.0;
+/// assert!((res.results[0].value - exact).abs() < exact * 1e-5);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct IntegrationRange {
     start: Real,
     length: Real,
@@ -224,10 +628,21 @@ pub enum CubaError {
     /// algorithm. The name of the algorithm and the number of dimensions
     /// attempted are given.
     BadComp(&'static str, usize),
+    /// The points given to Divonne as known peaks do not have the same
+    /// dimension as the integrand's input. The dimension of the given points
+    /// and of the integrand are given, in that order.
+    BadGivenDim(usize, usize),
+    /// The number of integration ranges given does not match the
+    /// dimension of the integrand's input. The number of ranges and the
+    /// dimension of the integrand are given, in that order.
+    BadRangeDim(usize, usize),
     /// The integration did not converge. Though the results did not reach
     /// the desired uncertainty, they still might be useful, and so are
     /// provided.
     DidNotConverge(CubaIntegrationResults),
+    /// The state file given to `Vegas::with_trained_statefile` cannot be
+    /// reused, for the given reason.
+    BadStateFile(StateFileError),
 }
 
 impl fmt::Display for CubaError {
@@ -242,7 +657,16 @@ impl fmt::Display for CubaError {
                 write!(fmt, "invalid number of outputs for algorithm {}: {}",
                        name, ncomp)
             },
-            &DidNotConverge(_) => write!(fmt, "integral did not converge")
+            &BadGivenDim(given, ndim) => {
+                write!(fmt, "dimension of given points ({}) does not match integrand dimension ({})",
+                       given, ndim)
+            },
+            &BadRangeDim(nranges, ndim) => {
+                write!(fmt, "number of integration ranges ({}) does not match integrand dimension ({})",
+                       nranges, ndim)
+            },
+            &DidNotConverge(_) => write!(fmt, "integral did not converge"),
+            &BadStateFile(ref err) => write!(fmt, "vegas state file {}", err),
         }
     }
 }
@@ -0,0 +1,160 @@
+use std::{cmp, fmt, mem, panic, thread};
+use std::sync::mpsc;
+
+type Job<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+struct Worker {
+    jobs: Option<mpsc::Sender<Job<'static>>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+/// A pool of worker threads, for evaluating batches of integrand points in
+/// parallel. Cuba's own parallelization (via `fork()`) is always turned off,
+/// as it is incompatible with Rust's safety guarantees; this is its
+/// replacement.
+///
+/// The threads are started when the pool is created, and stopped when it is
+/// dropped, so a single pool can (and should) be reused across many
+/// integrations.
+///
+/// Each point of a batch is evaluated independently, and the outputs are
+/// returned in the order of the inputs, so integration results are
+/// bit-for-bit identical regardless of the number of threads.
+///
+/// ```
+/// use integrators::{Real, Real2};
+/// use integrators::cuba::{ThreadPool, Vegas};
+///
+/// let pool = ThreadPool::new(4);
+/// let mut vegas = Vegas::new().with_maxeval(1000000)
+///                             .with_seed(1)
+///                             .with_nvec(1000);
+///
+/// let parallel = vegas.integrate_parallel(&pool, |(x, y): Real2| x * y,
+///                                         1e-3, 1e-12).unwrap();
+/// let serial = vegas.integrate_parallel(&ThreadPool::new(1),
+///                                       |(x, y): Real2| x * y,
+///                                       1e-3, 1e-12).unwrap();
+/// assert_eq!(parallel, serial);
+/// ```
+pub struct ThreadPool {
+    workers: Vec<Worker>,
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("ThreadPool")
+           .field("nthreads", &self.nthreads())
+           .finish()
+    }
+}
+
+impl ThreadPool {
+    /// Creates a pool which evaluates integrands on `nthreads` threads,
+    /// counting the calling thread. A pool of 0 or 1 threads evaluates
+    /// everything on the calling thread.
+    pub fn new(nthreads: usize) -> Self {
+        let workers = (1..cmp::max(nthreads, 1)).map(|_| {
+            let (jobs, queue) = mpsc::channel::<Job<'static>>();
+            let handle = thread::spawn(move || {
+                for job in queue {
+                    job()
+                }
+            });
+            Worker {
+                jobs: Some(jobs),
+                handle: Some(handle),
+            }
+        }).collect();
+
+        ThreadPool { workers }
+    }
+
+    /// The number of threads integrands are evaluated on, including the
+    /// calling thread.
+    pub fn nthreads(&self) -> usize {
+        self.workers.len() + 1
+    }
+
+    /// Applies `fun` to every point in `points`, splitting them into
+    /// contiguous chunks evaluated across the pool. The outputs are in the
+    /// same order as `points`.
+    ///
+    /// # Panics
+    /// If `fun` panics on any thread, the panic is resumed on the calling
+    /// thread once every thread has finished its chunk.
+    pub fn map<A, B, F>(&self, fun: &F, points: &[A]) -> Vec<B>
+        where A: Clone + Sync,
+              B: Send,
+              F: Fn(A) -> B + Sync
+    {
+        let nchunks = cmp::min(self.nthreads(), points.len());
+        if nchunks <= 1 {
+            return points.iter().cloned().map(fun).collect();
+        }
+
+        let chunk_size = (points.len() + nchunks - 1) / nchunks;
+        let mut chunks = points.chunks(chunk_size);
+        let first = chunks.next().expect("there are at least 2 chunks");
+
+        let (done, finished) = mpsc::channel::<(usize, thread::Result<Vec<B>>)>();
+        let mut njobs = 0;
+        for (worker, chunk) in self.workers.iter().zip(chunks) {
+            njobs += 1;
+            let index = njobs;
+            let done = done.clone();
+            let job: Job = Box::new(move || {
+                let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    chunk.iter().cloned().map(fun).collect::<Vec<B>>()
+                }));
+                let _ = done.send((index, res));
+            });
+            // This erases the lifetime of the borrows of `fun` and `points`.
+            // It is sound because we do not return until every job has either
+            // run to completion, or been dropped without running.
+            let job: Job<'static> = unsafe { mem::transmute(job) };
+            let jobs = worker.jobs.as_ref().expect("only taken on drop");
+            if let Err(mpsc::SendError(job)) = jobs.send(job) {
+                job()
+            }
+        }
+        drop(done);
+
+        let mut outputs: Vec<Option<thread::Result<Vec<B>>>> =
+            (0..njobs + 1).map(|_| None).collect();
+        outputs[0] = Some(panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            first.iter().cloned().map(fun).collect::<Vec<B>>()
+        })));
+        for _ in 0..njobs {
+            match finished.recv() {
+                Ok((index, res)) => outputs[index] = Some(res),
+                // Every remaining job was dropped without running.
+                Err(_) => break,
+            }
+        }
+
+        let mut results = Vec::with_capacity(points.len());
+        for output in outputs.into_iter() {
+            match output {
+                Some(Ok(res)) => results.extend(res),
+                Some(Err(err)) => panic::resume_unwind(err),
+                None => panic!("thread pool worker exited unexpectedly"),
+            }
+        }
+        results
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing every queue first lets the workers shut down concurrently.
+        for worker in self.workers.iter_mut() {
+            worker.jobs.take();
+        }
+        for worker in self.workers.iter_mut() {
+            if let Some(handle) = worker.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::{fmt, ptr};
+use std::os::raw::c_void;
+
+use ::bindings;
+
+/// A handle on Cuba's "spinning" worker processes. An integrator holding a
+/// `CubaSpin` (see each integrator's `with_spin`) passes it to every call to
+/// Cuba, so any workers Cuba starts are kept alive between integrations,
+/// rather than being started and stopped each time. The workers are stopped
+/// when the `CubaSpin` is dropped, or by calling `wait()`.
+///
+/// This is currently a placeholder: this crate turns Cuba's `fork()`-based
+/// parallelization off, as it is incompatible with Rust's safety guarantees,
+/// so Cuba starts no workers at all and the handle always stays empty. Parallel evaluation of the integrand is instead
+/// done by a `ThreadPool`, whose threads likewise persist until it is
+/// dropped.
+///
+/// Cloning a `CubaSpin` yields a new, empty handle: workers are never shared
+/// between integrators.
+pub struct CubaSpin {
+    spin: *mut c_void,
+}
+
+// The handle refers to worker processes, not to any thread-local state, so
+// it may be moved to another thread. It is only ever used through
+// `&mut self`.
+unsafe impl Send for CubaSpin {}
+
+impl CubaSpin {
+    /// Creates an empty handle. Workers are started by the first
+    /// integration which uses it.
+    pub fn new() -> Self {
+        CubaSpin {
+            spin: ptr::null_mut()
+        }
+    }
+
+    /// Whether Cuba has started any workers on this handle.
+    pub fn is_running(&self) -> bool {
+        !self.spin.is_null()
+    }
+
+    /// Stops any running workers. They are started again by the next
+    /// integration which uses this handle.
+    pub fn wait(&mut self) {
+        if self.is_running() {
+            unsafe {
+                bindings::cubawait(&mut self.spin as *mut *mut c_void as *mut _)
+            };
+            self.spin = ptr::null_mut();
+        }
+    }
+
+    /// The pointer to pass as the `spin` argument of Cuba's routines.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut c_void {
+        &mut self.spin as *mut *mut c_void as *mut c_void
+    }
+}
+
+impl Default for CubaSpin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clone for CubaSpin {
+    fn clone(&self) -> Self {
+        CubaSpin::new()
+    }
+}
+
+impl fmt::Debug for CubaSpin {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CubaSpin")
+           .field("running", &self.is_running())
+           .finish()
+    }
+}
+
+impl Drop for CubaSpin {
+    fn drop(&mut self) {
+        self.wait()
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "cuba")]
+mod test_spin {
+    use std::ptr;
+    use std::os::raw::c_void;
+
+    use super::CubaSpin;
+    use ::cuba::spin_ptr;
+
+    #[test]
+    fn test_spin_ptr() {
+        assert!(spin_ptr(&mut None).is_null());
+
+        let mut spin = Some(CubaSpin::new());
+        let handle = spin_ptr(&mut spin) as *mut *mut c_void;
+        assert!(!handle.is_null());
+        assert!(!spin.as_ref().unwrap().is_running());
+
+        // Cuba stores its workers through the pointer it is given...
+        let mut workers = 0u8;
+        unsafe { *handle = &mut workers as *mut u8 as *mut c_void };
+        assert!(spin.as_ref().unwrap().is_running());
+        assert_eq!(format!("{:?}", spin.as_ref().unwrap()), "CubaSpin { running: true }");
+
+        // ...and clears it once they have stopped.
+        unsafe { *handle = ptr::null_mut() };
+        let mut spin = spin.unwrap();
+        assert!(!spin.is_running());
+        spin.wait();
+        assert!(!spin.is_running());
+        assert!(!spin.clone().is_running());
+    }
+}
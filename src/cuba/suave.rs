@@ -1,15 +1,17 @@
-use std::{mem, ptr};
+use std::ptr;
 use std::os::raw::{c_int, c_longlong};
 
 use ::bindings;
-use ::ffi::LandingPad;
 use ::traits::{IntegrandInput, IntegrandOutput};
-use ::{Integrator, Real};
+use ::{DynIntegrator, Integrator, Real};
 
-use super::{cuba_integrand, CubaError, CubaIntegrationResult, CubaIntegrationResults,
+use super::{integrate_dyn, integrate_scalar, integrate_vectorized, CubaError,
+            CubaIntegrationResults, CubaOutputs, CubaRoutine, CubaSpin, IntegrationRange,
+            RawIntegrand, ThreadPool,
+            spin_ptr,
             RandomNumberSource};
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Suave {
     mineval: usize,
     maxeval: usize,
@@ -17,6 +19,9 @@ pub struct Suave {
     nnew: usize,
     nmin: usize,
     flatness: Real,
+    nvec: usize,
+    spin: Option<CubaSpin>,
+    ranges: Option<Vec<IntegrationRange>>,
     flags: c_int,
 }
 
@@ -29,6 +34,9 @@ impl Default for Suave {
             nnew: 1000,
             nmin: 5,
             flatness: 25 as Real,
+            nvec: 1,
+            spin: None,
+            ranges: None,
             flags: 0,
         }
     }
@@ -75,95 +83,138 @@ impl Suave {
         }
     }
 
+    /// Set the maximum number of points passed to the integrand at once by
+    /// `integrate_vectorized`. (Default = 1)
+    pub fn with_nvec(self, nvec: usize) -> Self {
+        Suave {
+            nvec, ..self
+        }
+    }
+
     /// Set the random number generator source.
     pub fn with_rng(self, rng: RandomNumberSource) -> Self {
         Suave {
-            flags: (self.flags & !0x8) & match rng {
+            flags: (self.flags & !0x8) | match rng {
                 RandomNumberSource::Sobol => 0,
                 RandomNumberSource::MersenneTwister => 8,
             }, ..self
         }
     }
+
+    /// Keep Cuba's worker processes alive between integrations, using the
+    /// given handle. See `CubaSpin`.
+    pub fn with_spin(self, spin: CubaSpin) -> Self {
+        Suave {
+            spin: Some(spin), ..self
+        }
+    }
+
+    /// Removes the worker handle, if any, from the integrator.
+    pub fn take_spin(&mut self) -> Option<CubaSpin> {
+        self.spin.take()
+    }
+
+    /// Integrate over the given range of each dimension, rather than the
+    /// unit hypercube. The integrand is given the transformed coordinates,
+    /// and the results include the Jacobian of the transformation. There
+    /// must be one range for each dimension of the integrand, or else
+    /// integration fails with `CubaError::BadRangeDim`.
+    pub fn with_ranges(self, ranges: Vec<IntegrationRange>) -> Self {
+        Suave {
+            ranges: Some(ranges), ..self
+        }
+    }
+
+    /// Integrate over the unit hypercube again.
+    pub fn without_ranges(self) -> Self {
+        Suave {
+            ranges: None, ..self
+        }
+    }
+
+    /// The range of each dimension, if given.
+    pub fn ranges(&self) -> Option<&[IntegrationRange]> {
+        self.ranges.as_ref().map(|ranges| &ranges[..])
+    }
+
+    /// Integrates a vectorized integrand, which is given a slice of up to
+    /// `nvec` points at a time and must return one output for each. See the
+    /// `cuba` module documentation for details.
+    pub fn integrate_vectorized<A, B, F>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput,
+              B: IntegrandOutput,
+              F: FnMut(&[A]) -> Vec<B>
+    {
+        let nvec = self.nvec;
+        integrate_vectorized(self, nvec, fun, epsrel, epsabs)
+    }
+
+    /// Integrates `fun` in parallel, by splitting each batch of up to `nvec`
+    /// points across the threads of `pool`. `nvec` should be set well above
+    /// the number of threads for this to be worthwhile. See `ThreadPool`.
+    pub fn integrate_parallel<A, B, F>(&mut self, pool: &ThreadPool, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput + Clone + Sync,
+              B: IntegrandOutput + Send,
+              F: Fn(A) -> B + Sync
+    {
+        self.integrate_vectorized(|points: &[A]| pool.map(&fun, points),
+                                  epsrel, epsabs)
+    }
+}
+
+impl CubaRoutine for Suave {
+    unsafe fn call(&mut self, raw: &RawIntegrand, epsrel: Real, epsabs: Real)
+            -> Result<CubaIntegrationResults, CubaError> {
+        let mut nregions = 0;
+        let mut out = CubaOutputs::new(raw.ncomp);
+        bindings::llSuave(raw.ndim as c_int, raw.ncomp as c_int,
+                          raw.integrand, raw.userdata,
+                          raw.nvec as c_longlong,
+                          epsrel,
+                          epsabs,
+                          self.flags,
+                          self.seed as c_int,
+                          self.mineval as c_longlong,
+                          self.maxeval as c_longlong,
+                          self.nnew as c_longlong,
+                          self.nmin as c_longlong,
+                          self.flatness,
+                          // statefile
+                          ptr::null(),
+                          spin_ptr(&mut self.spin),
+                          &mut nregions,
+                          &mut out.neval,
+                          &mut out.fail,
+                          out.value.as_mut_ptr(),
+                          out.error.as_mut_ptr(),
+                          out.prob.as_mut_ptr());
+        out.nregions = Some(nregions);
+        out.into_results("suave", raw.ndim, raw.ncomp)
+    }
+
+    fn ranges(&self) -> Option<&[IntegrationRange]> {
+        Suave::ranges(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "suave"
+    }
 }
 
 impl Integrator for Suave {
     type Success = CubaIntegrationResults;
-    type Failure = super::CubaError;
-    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+    type Failure = CubaError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
         where A: IntegrandInput,
               B: IntegrandOutput
     {
-        // Using cuba's parallelization via fork() would deeply break Rust's
-        // concurrency model and safety guarantees. So, we'll turn it off.
-        unsafe { bindings::cubacores(0, 0) };
-
-        let (ndim, ncomp) = {
-            let inputs = A::input_size();
-            let outputs = fun(A::from_args(&vec![0.5; inputs][..])).output_size();
-            (inputs, outputs)
-        };
+        integrate_scalar(self, fun, epsrel, epsabs)
+    }
+}
 
-        let mut nregions = 0;
-        let mut neval = 0;
-        let mut fail = 0;
-        let (mut value, mut error, mut prob) =
-                (vec![0.0; ncomp], vec![0.0; ncomp], vec![0.0; ncomp]);
-
-        let mut lp = LandingPad::new(fun);
-        unsafe {
-            bindings::llSuave(ndim as c_int, ncomp as c_int,
-                              Some(cuba_integrand::<A, B, F>), mem::transmute(&mut lp),
-                              1 /* nvec */,
-                              epsrel,
-                              epsabs,
-                              self.flags,
-                              self.seed as c_int,
-                              self.mineval as c_longlong,
-                              self.maxeval as c_longlong,
-                              self.nnew as c_longlong,
-                              self.nmin as c_longlong,
-                              self.flatness,
-                              // statefile
-                              ptr::null(),
-                              // spin
-                              ptr::null_mut(),
-                              &mut nregions,
-                              &mut neval,
-                              &mut fail,
-                              value.as_mut_ptr(),
-                              error.as_mut_ptr(),
-                              prob.as_mut_ptr());
-        }
-        lp.maybe_resume_unwind();
-
-        if fail == 0 {
-            Ok(CubaIntegrationResults {
-                nregions: Some(nregions), neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            })
-        } else if fail == -1 {
-            // `baddim`
-            Err(CubaError::BadDim("suave", ndim))
-        } else if fail == -2 {
-            // `badcomp`
-            Err(CubaError::BadComp("suave", ncomp))
-        } else if fail == 1 {
-            Err(CubaError::DidNotConverge(CubaIntegrationResults {
-                nregions: None, neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            }))
-        } else {
-            unreachable!("Suave returned invalid failure code: {}", fail)
-        }
+impl DynIntegrator for Suave {
+    fn integrate_dyn(&mut self, ndim: usize, ncomp: usize, fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                     epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure> {
+        integrate_dyn(self, ndim, ncomp, fun, epsrel, epsabs)
     }
 }
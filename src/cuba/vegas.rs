@@ -1,15 +1,104 @@
-use std::{mem, ptr};
+use std::{fmt, fs, io, ptr};
+use std::io::Read;
+use std::ffi::{CStr, CString};
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 use std::os::raw::{c_int, c_longlong};
+use std::path::Path;
 
 use ::bindings;
-use ::ffi::LandingPad;
 use ::traits::{IntegrandInput, IntegrandOutput};
-use ::{Integrator, Real};
+use ::{DynIntegrator, Integrator, Real};
 
-use super::{cuba_integrand, CubaError, CubaIntegrationResult, CubaIntegrationResults,
+use super::{integrate_dyn, integrate_scalar, integrate_vectorized, CubaError,
+            CubaIntegrationResults, CubaOutputs, CubaRoutine, CubaSpin, IntegrationRange,
+            RawIntegrand, ThreadPool,
+            spin_ptr,
             RandomNumberSource};
 
-#[derive(Copy, Clone, Debug)]
+/// Flag bit which keeps the state file after a successful integration.
+const RETAIN_STATEFILE: c_int = 0x10;
+/// Flag bit which takes only the grid from a state file, discarding the
+/// results accumulated by the previous integration.
+const GRID_ONLY: c_int = 0x20;
+
+#[cfg(unix)]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+#[cfg(not(unix))]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    CString::new(path.to_str()?).ok()
+}
+
+#[cfg(unix)]
+fn cstr_to_path(s: &CStr) -> &Path {
+    Path::new(OsStr::from_bytes(s.to_bytes()))
+}
+
+#[cfg(not(unix))]
+fn cstr_to_path(s: &CStr) -> &Path {
+    Path::new(s.to_str().expect("checked in with_statefile"))
+}
+
+/// Cuba's state files begin with a 64 bit signature: "CUBA" in the low 32
+/// bits, then the number of dimensions in the next 16, the number of
+/// components in the next 12, and the algorithm in the top 4.
+const STATE_SIGNATURE: u64 = 0x4142_5543;
+/// The algorithm of Vegas, in the signature of its state files.
+const STATE_VEGAS: u64 = 1;
+
+/// Why a Vegas state file cannot be reused. See
+/// `Vegas::with_trained_statefile`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateFileError {
+    /// The file could not be read.
+    Unreadable(io::ErrorKind),
+    /// The file is too short to hold the state's header.
+    Truncated,
+    /// The file was not written by Cuba's Vegas.
+    NotVegas,
+    /// The file was written by an integration of other dimensions. The
+    /// number of dimensions and of components of the file are given.
+    Mismatch(usize, usize),
+}
+
+impl fmt::Display for StateFileError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateFileError::Unreadable(kind) => write!(fmt, "cannot be read: {:?}", kind),
+            StateFileError::Truncated => write!(fmt, "is truncated"),
+            StateFileError::NotVegas => write!(fmt, "was not written by Vegas"),
+            StateFileError::Mismatch(ndim, ncomp) =>
+                write!(fmt, "was written for {} dimensions and {} components", ndim, ncomp),
+        }
+    }
+}
+
+/// Reads the number of dimensions and components of the integration which
+/// wrote the Vegas state file at `path`.
+fn read_state_header(path: &Path) -> Result<(usize, usize), StateFileError> {
+    let mut header = [0u8; 8];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => StateFileError::Truncated,
+            kind => StateFileError::Unreadable(kind),
+        })?;
+    let signature = u64::from_ne_bytes(header);
+    if signature & 0xffff_ffff != STATE_SIGNATURE || signature >> 60 != STATE_VEGAS {
+        return Err(StateFileError::NotVegas);
+    }
+    Ok((((signature >> 32) & 0xffff) as usize, ((signature >> 48) & 0xfff) as usize))
+}
+
+/// The number of slots in Cuba's internal table of Vegas grids.
+pub const MAX_GRIDNO: c_int = 10;
+
+#[derive(Clone, Debug)]
 pub struct Vegas {
     mineval: usize,
     maxeval: usize,
@@ -17,7 +106,12 @@ pub struct Vegas {
     nstart: usize,
     nincrease: usize,
     nbatch: usize,
-    gridno: u8,
+    gridno: c_int,
+    statefile: Option<CString>,
+    check_statefile: bool,
+    nvec: usize,
+    spin: Option<CubaSpin>,
+    ranges: Option<Vec<IntegrationRange>>,
     flags: c_int,
 }
 
@@ -31,6 +125,11 @@ impl Default for Vegas {
             nincrease: 500,
             nbatch: 1000,
             gridno: 0,
+            statefile: None,
+            check_statefile: false,
+            nvec: 1,
+            spin: None,
+            ranges: None,
             flags: 0
         }
     }
@@ -78,94 +177,244 @@ impl Vegas {
         }
     }
 
+    /// Set the maximum number of points passed to the integrand at once by
+    /// `integrate_vectorized`. (Default = 1)
+    pub fn with_nvec(self, nvec: usize) -> Self {
+        Vegas {
+            nvec, ..self
+        }
+    }
+
+    /// Set the slot in Cuba's internal table of grids to use. A grid number
+    /// from 1 to `MAX_GRIDNO` starts from the grid in that slot (if any), and
+    /// stores the adapted grid back into it afterwards. A negative grid number
+    /// uses the grid in that slot without updating it, and 0 uses no slot.
+    /// (Default = 0)
+    ///
+    /// The table is global to the process, and is shared by every `Vegas`
+    /// integration with the same grid number, so integrands sharing a slot
+    /// should have the same number of dimensions.
+    ///
+    /// Returns `None` if `gridno` is outside of [-`MAX_GRIDNO`, `MAX_GRIDNO`].
+    pub fn with_gridno(self, gridno: c_int) -> Option<Self> {
+        if (gridno >= -MAX_GRIDNO) & (gridno <= MAX_GRIDNO) {
+            Some(Vegas {
+                gridno, ..self
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Set a file to store the state of the integration in. If the file
+    /// already exists, Vegas resumes from the state it contains, so an
+    /// interrupted integration picks up where it left off. The file is
+    /// deleted once the integration completes successfully, unless
+    /// `with_retain_statefile` is set.
+    ///
+    /// Returns `None` if the path contains a NUL byte, or, on platforms
+    /// other than Unix, is not valid UTF-8.
+    pub fn with_statefile<P: AsRef<Path>>(self, path: P) -> Option<Self> {
+        let statefile = path_to_cstring(path.as_ref())?;
+        Some(Vegas {
+            statefile: Some(statefile),
+            check_statefile: false,
+            ..self
+        })
+    }
+
+    /// Stop using a state file.
+    pub fn without_statefile(self) -> Self {
+        Vegas {
+            statefile: None,
+            check_statefile: false,
+            ..self
+        }
+    }
+
+    /// The state file in use, if any.
+    pub fn statefile(&self) -> Option<&Path> {
+        self.statefile.as_ref().map(|s| cstr_to_path(s))
+    }
+
+    /// Keep the state file after a successful integration, rather than
+    /// deleting it. (Default = false)
+    pub fn with_retain_statefile(self, retain: bool) -> Self {
+        Vegas {
+            flags: if retain {
+                self.flags | RETAIN_STATEFILE
+            } else {
+                self.flags & !RETAIN_STATEFILE
+            }, ..self
+        }
+    }
+
+    /// Only take the adapted grid from the state file, and reset the
+    /// accumulated results. Together with `with_retain_statefile`, this
+    /// allows a grid trained on one integrand to warm-start the integration
+    /// of another. (Default = false)
+    pub fn with_grid_only(self, grid_only: bool) -> Self {
+        Vegas {
+            flags: if grid_only {
+                self.flags | GRID_ONLY
+            } else {
+                self.flags & !GRID_ONLY
+            }, ..self
+        }
+    }
+
+    /// Reuses the trained state in the existing file at `path`, retaining
+    /// it afterwards. Unlike `with_statefile`, this checks that the file
+    /// exists, so a typo in the path doesn't silently start a new
+    /// integration from scratch, and before each integration, that the
+    /// file's header shows it was written by Vegas for an integrand of the
+    /// same dimensions. Otherwise, integration fails with
+    /// `CubaError::BadStateFile`, where Cuba would ignore the file.
+    pub fn with_trained_statefile<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        if !fs::metadata(path)?.is_file() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("not a file: {}", path.display())));
+        }
+        self.with_statefile(path)
+            .map(|vegas| Vegas {
+                check_statefile: true,
+                ..vegas.with_retain_statefile(true)
+            })
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                                          format!("invalid state file path: {}",
+                                                  path.display())))
+    }
+
     /// Set the random number generator source.
     pub fn with_rng(self, rng: RandomNumberSource) -> Self {
         Vegas {
-            flags: (self.flags & !0x8) & match rng {
+            flags: (self.flags & !0x8) | match rng {
                 RandomNumberSource::Sobol => 0,
                 RandomNumberSource::MersenneTwister => 8,
             }, ..self
         }
     }
+
+    /// Keep Cuba's worker processes alive between integrations, using the
+    /// given handle. See `CubaSpin`.
+    pub fn with_spin(self, spin: CubaSpin) -> Self {
+        Vegas {
+            spin: Some(spin), ..self
+        }
+    }
+
+    /// Removes the worker handle, if any, from the integrator.
+    pub fn take_spin(&mut self) -> Option<CubaSpin> {
+        self.spin.take()
+    }
+
+    /// Integrate over the given range of each dimension, rather than the
+    /// unit hypercube. The integrand is given the transformed coordinates,
+    /// and the results include the Jacobian of the transformation. There
+    /// must be one range for each dimension of the integrand, or else
+    /// integration fails with `CubaError::BadRangeDim`.
+    pub fn with_ranges(self, ranges: Vec<IntegrationRange>) -> Self {
+        Vegas {
+            ranges: Some(ranges), ..self
+        }
+    }
+
+    /// Integrate over the unit hypercube again.
+    pub fn without_ranges(self) -> Self {
+        Vegas {
+            ranges: None, ..self
+        }
+    }
+
+    /// The range of each dimension, if given.
+    pub fn ranges(&self) -> Option<&[IntegrationRange]> {
+        self.ranges.as_ref().map(|ranges| &ranges[..])
+    }
+
+    /// Integrates a vectorized integrand, which is given a slice of up to
+    /// `nvec` points at a time and must return one output for each. See the
+    /// `cuba` module documentation for details.
+    pub fn integrate_vectorized<A, B, F>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput,
+              B: IntegrandOutput,
+              F: FnMut(&[A]) -> Vec<B>
+    {
+        let nvec = self.nvec;
+        integrate_vectorized(self, nvec, fun, epsrel, epsabs)
+    }
+
+    /// Integrates `fun` in parallel, by splitting each batch of up to `nvec`
+    /// points across the threads of `pool`. `nvec` should be set well above
+    /// the number of threads for this to be worthwhile. See `ThreadPool`.
+    pub fn integrate_parallel<A, B, F>(&mut self, pool: &ThreadPool, fun: F, epsrel: Real, epsabs: Real) -> Result<CubaIntegrationResults, CubaError>
+        where A: IntegrandInput + Clone + Sync,
+              B: IntegrandOutput + Send,
+              F: Fn(A) -> B + Sync
+    {
+        self.integrate_vectorized(|points: &[A]| pool.map(&fun, points),
+                                  epsrel, epsabs)
+    }
+}
+
+impl CubaRoutine for Vegas {
+    unsafe fn call(&mut self, raw: &RawIntegrand, epsrel: Real, epsabs: Real)
+            -> Result<CubaIntegrationResults, CubaError> {
+        if let (true, Some(statefile)) = (self.check_statefile, self.statefile()) {
+            let (ndim, ncomp) = read_state_header(statefile).map_err(CubaError::BadStateFile)?;
+            if (ndim, ncomp) != (raw.ndim, raw.ncomp) {
+                return Err(CubaError::BadStateFile(StateFileError::Mismatch(ndim, ncomp)));
+            }
+        }
+        let mut out = CubaOutputs::new(raw.ncomp);
+        bindings::llVegas(raw.ndim as c_int, raw.ncomp as c_int,
+                          raw.integrand, raw.userdata,
+                          raw.nvec as c_longlong,
+                          epsrel,
+                          epsabs,
+                          self.flags,
+                          self.seed as c_int,
+                          self.mineval as c_longlong,
+                          self.maxeval as c_longlong,
+                          self.nstart as c_longlong,
+                          self.nincrease as c_longlong,
+                          self.nbatch as c_longlong,
+                          self.gridno,
+                          self.statefile.as_ref()
+                              .map(|s| s.as_ptr())
+                              .unwrap_or(ptr::null()),
+                          spin_ptr(&mut self.spin),
+                          &mut out.neval,
+                          &mut out.fail,
+                          out.value.as_mut_ptr(),
+                          out.error.as_mut_ptr(),
+                          out.prob.as_mut_ptr());
+        out.into_results("vegas", raw.ndim, raw.ncomp)
+    }
+
+    fn ranges(&self) -> Option<&[IntegrationRange]> {
+        Vegas::ranges(self)
+    }
+
+    fn name(&self) -> &'static str {
+        "vegas"
+    }
 }
 
 impl Integrator for Vegas {
     type Success = CubaIntegrationResults;
-    type Failure = super::CubaError;
-    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+    type Failure = CubaError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
         where A: IntegrandInput,
               B: IntegrandOutput
     {
-        // Using cuba's parallelization via fork() would deeply break Rust's
-        // concurrency model and safety guarantees. So, we'll turn it off.
-        unsafe { bindings::cubacores(0, 0) };
-
-        let (ndim, ncomp) = {
-            let inputs = A::input_size();
-            let outputs = fun(A::from_args(&vec![0.5; inputs][..])).output_size();
-            (inputs, outputs)
-        };
-
-        let mut neval = 0;
-        let mut fail = 0;
-        let (mut value, mut error, mut prob) =
-                (vec![0.0; ncomp], vec![0.0; ncomp], vec![0.0; ncomp]);
-
-        let mut lp = LandingPad::new(fun);
-        unsafe {
-            bindings::llVegas(ndim as c_int, ncomp as c_int,
-                              Some(cuba_integrand::<A, B, F>), mem::transmute(&mut lp),
-                              1 /* nvec */,
-                              epsrel,
-                              epsabs,
-                              self.flags,
-                              self.seed as c_int,
-                              self.mineval as c_longlong,
-                              self.maxeval as c_longlong,
-                              self.nstart as c_longlong,
-                              self.nincrease as c_longlong,
-                              self.nbatch as c_longlong,
-                              self.gridno as c_int,
-                              // statefile
-                              ptr::null(),
-                              // spin
-                              ptr::null_mut(),
-                              &mut neval,
-                              &mut fail,
-                              value.as_mut_ptr(),
-                              error.as_mut_ptr(),
-                              prob.as_mut_ptr());
-        }
-        lp.maybe_resume_unwind();
-
-        if fail == 0 {
-            Ok(CubaIntegrationResults {
-                nregions: None, neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            })
-        } else if fail == -1 {
-            // `baddim`
-            Err(CubaError::BadDim("vegas", ndim))
-        } else if fail == -2 {
-            // `badcomp`
-            Err(CubaError::BadComp("vegas", ncomp))
-        } else if fail == 1 {
-            Err(CubaError::DidNotConverge(CubaIntegrationResults {
-                nregions: None, neval,
-                results: value.iter().zip(error.iter()).zip(prob.iter())
-                              .map(|((&value, &error), &prob)|
-                                     CubaIntegrationResult {
-                                         value, error, prob
-                                     })
-                              .collect()
-            }))
-        } else {
-            unreachable!("Vegas returned invalid failure code: {}", fail)
-        }
+        integrate_scalar(self, fun, epsrel, epsabs)
+    }
+}
+
+impl DynIntegrator for Vegas {
+    fn integrate_dyn(&mut self, ndim: usize, ncomp: usize, fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                     epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure> {
+        integrate_dyn(self, ndim, ncomp, fun, epsrel, epsabs)
     }
 }
@@ -0,0 +1,728 @@
+//! Integration over regions other than the unit hypercube, with any
+//! `Integrator`.
+//!
+//! Every integrator in this crate integrates over a fixed region, usually
+//! the unit hypercube. The types in this module describe other regions,
+//! including infinite ones, by a change of variables from the unit
+//! hypercube, and integrate over them with any integrator by applying the
+//! change of variables and its Jacobian to the integrand. The integrand is
+//! given points of the region itself.
+//!
+//! Regions implement the `Domain` trait. This module provides
+//! `HyperRectangle`, whose axes may be infinite, `Simplex`, `Ball`,
+//! `Annulus` and `Sphere`, and other regions can be integrated over by
+//! implementing `Domain` for them.
+//!
+//! ```
+//! # #[cfg(feature = "native")] {
+//! use std::f64::consts::PI;
+//! use integrators::Real2;
+//! use integrators::domain::{Axis, Domain, HyperRectangle};
+//! use integrators::native::Cubature;
+//!
+//! // A Gaussian over the whole plane
+//! let plane = HyperRectangle::new(vec![Axis::Whole, Axis::Whole]).unwrap();
+//! let res = plane.integrate(&mut Cubature::new(1000000),
+//!                           |(x, y): Real2| (-(x * x + y * y) / 2.0).exp(),
+//!                           1e-8, 1e-12)
+//!                .unwrap();
+//! assert!((res.results[0].value - 2.0 * PI).abs() < 1e-7);
+//! # }
+//! ```
+
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+use std::marker;
+
+use super::traits::{IntegrandInput, IntegrandOutput, Integrator};
+use super::{Real, Real2, Real3};
+
+/// A region of integration, mapped from the unit hypercube.
+///
+/// Only `map` needs to be implemented. The integral over the region is then
+/// the integral over the unit hypercube of the integrand at the mapped
+/// point, times the Jacobian of the map.
+///
+/// ```
+/// use integrators::{Real, Real2};
+/// use integrators::domain::Domain;
+/// # #[cfg(feature = "native")] {
+/// use integrators::native::Cubature;
+///
+/// // The region under the parabola y = x^2, for x from 0 to 1
+/// struct UnderParabola;
+///
+/// impl Domain for UnderParabola {
+///     type Unit = Real2;
+///     type Point = Real2;
+///     fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+///         point[0] = unit[0];
+///         point[1] = unit[1] * unit[0] * unit[0];
+///         unit[0] * unit[0]
+///     }
+/// }
+///
+/// let res = UnderParabola.integrate(&mut Cubature::new(10000), |_: Real2| 1.0, 1e-10, 0.0)
+///                        .unwrap();
+/// assert!((res.results[0].value - 1.0 / 3.0).abs() < 1e-10);
+/// # }
+/// ```
+pub trait Domain {
+    /// A point of the unit hypercube the region is mapped from.
+    type Unit: IntegrandInput + IntegrandOutput;
+    /// A point of the region.
+    type Point: IntegrandInput;
+
+    /// Maps `unit`, a point of the unit hypercube, to `point`, a point of the
+    /// region. Returns the Jacobian of the map. Points on the boundary of
+    /// the unit hypercube may map to infinity.
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real;
+
+    /// Integrates `fun` over the region with `integrator`, which must
+    /// integrate over the unit hypercube. The integrand is taken to vanish
+    /// at points which map to infinity.
+    fn integrate<I, B, F>(&self, integrator: &mut I, mut fun: F, epsrel: Real, epsabs: Real) -> Result<I::Success, I::Failure>
+        where I: Integrator,
+              B: IntegrandOutput,
+              F: FnMut(Self::Point) -> B
+    {
+        // The number of outputs, from the center of the unit hypercube, which
+        // maps to a finite point of the region
+        let mut unit = vec![0.5; Self::Unit::input_size()];
+        let mut point = vec![0.0; Self::Point::input_size()];
+        self.map(&unit, &mut point);
+        let ncomp = fun(Self::Point::from_args(&point)).output_size();
+
+        integrator.integrate(|u: Self::Unit| {
+            u.into_args(&mut unit);
+            let jacobian = self.map(&unit, &mut point);
+            if jacobian.is_finite() && point.iter().all(|x| x.is_finite()) {
+                Mapped::Scaled(fun(Self::Point::from_args(&point)), jacobian)
+            } else {
+                Mapped::Zero(ncomp)
+            }
+        }, epsrel, epsabs)
+    }
+}
+
+/// The range of one axis of a `HyperRectangle`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Axis {
+    /// The finite range from the first value to the second. If the second
+    /// is smaller, the integral over the axis changes sign.
+    Finite(Real, Real),
+    /// The range from the given value to positive infinity.
+    Above(Real),
+    /// The range from negative infinity to the given value.
+    Below(Real),
+    /// The whole real line.
+    Whole,
+}
+
+impl Axis {
+    /// The range from `low` to `high`, either of which may be infinite.
+    /// Returns `None` if either is NaN, or if an infinite end is on the
+    /// wrong side of the other.
+    pub fn new(low: Real, high: Real) -> Option<Self> {
+        match (low.is_finite(), high.is_finite()) {
+            (true, true) => Some(Axis::Finite(low, high)),
+            (true, false) if high > low => Some(Axis::Above(low)),
+            (false, true) if low < high => Some(Axis::Below(high)),
+            (false, false) if low < high => Some(Axis::Whole),
+            _ => None,
+        }
+    }
+
+    /// Maps `t`, in [0, 1], onto the axis. Returns the mapped value and the
+    /// Jacobian of the map.
+    fn map(&self, t: Real, transform: AxisTransform, scale: Real) -> (Real, Real) {
+        match *self {
+            Axis::Finite(low, high) => (low + t * (high - low), high - low),
+            Axis::Above(low) => {
+                let (x, jac) = transform.half_line(t);
+                (low + scale * x, scale * jac)
+            },
+            Axis::Below(high) => {
+                let (x, jac) = transform.half_line(1.0 - t);
+                (high - scale * x, scale * jac)
+            },
+            Axis::Whole => {
+                let (x, jac) = transform.whole_line(t);
+                (scale * x, scale * jac)
+            },
+        }
+    }
+}
+
+/// The change of variables mapping [0, 1] onto an infinite axis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AxisTransform {
+    /// `x = t / (1 - t)` for a half line, and `x = (2t - 1) / (t (1 - t))`
+    /// for the whole line. Integrands which decay like `1 / x^2` remain
+    /// bounded, so this suits integrands with algebraic tails.
+    Rational,
+    /// `x = atanh(t)` for a half line, and `x = atanh(2t - 1)` for the whole
+    /// line. Integrands which decay exponentially vanish quickly towards the
+    /// ends of [0, 1], so this suits integrands with exponential or Gaussian
+    /// tails.
+    Tanh,
+}
+
+impl AxisTransform {
+    /// Maps `t` in [0, 1] to [0, infinity], with the Jacobian.
+    fn half_line(&self, t: Real) -> (Real, Real) {
+        match *self {
+            AxisTransform::Rational => (t / (1.0 - t), (1.0 - t).powi(2).recip()),
+            AxisTransform::Tanh => (t.atanh(), (1.0 - t * t).recip()),
+        }
+    }
+
+    /// Maps `t` in [0, 1] to the whole real line, with the Jacobian.
+    fn whole_line(&self, t: Real) -> (Real, Real) {
+        match *self {
+            AxisTransform::Rational => {
+                let q = t * (1.0 - t);
+                ((2.0 * t - 1.0) / q, (2.0 * t * t - 2.0 * t + 1.0) / (q * q))
+            },
+            AxisTransform::Tanh => {
+                let u = 2.0 * t - 1.0;
+                (u.atanh(), 2.0 / (1.0 - u * u))
+            },
+        }
+    }
+}
+
+/// An integrand's output, multiplied by the Jacobian of a change of
+/// variables.
+enum Mapped<B> {
+    Scaled(B, Real),
+    /// The output at a point mapped to infinity, where the integrand is
+    /// taken to vanish, with the number of outputs.
+    Zero(usize),
+}
+
+impl<B: IntegrandOutput> IntegrandOutput for Mapped<B> {
+    fn output_size(&self) -> usize {
+        match *self {
+            Mapped::Scaled(ref output, _) => output.output_size(),
+            Mapped::Zero(ncomp) => ncomp,
+        }
+    }
+
+    fn into_args(&self, args: &mut [Real]) {
+        match *self {
+            Mapped::Scaled(ref output, jacobian) => {
+                output.into_args(args);
+                for arg in args.iter_mut() {
+                    *arg *= jacobian;
+                }
+            },
+            Mapped::Zero(_) => {
+                for arg in args.iter_mut() {
+                    *arg = 0.0;
+                }
+            },
+        }
+    }
+}
+
+/// A region which is the product of a range along each axis, any of which
+/// may be infinite. Infinite axes are mapped from [0, 1] by an
+/// `AxisTransform`, scaled by `scale`, and finite axes by a linear map.
+///
+/// Integrands over infinite ranges must decay towards infinity; points
+/// which map to infinity contribute nothing. The scale should be about
+/// the width of the integrand, for the transformed integrand to be well
+/// spread over the unit hypercube.
+///
+/// ```
+/// use integrators::Real;
+/// use integrators::domain::{Axis, AxisTransform, Domain, HyperRectangle};
+/// # #[cfg(feature = "native")] {
+/// use integrators::native::GaussKronrod;
+///
+/// // The integral of 1 / (1 + x^2) over [0, infinity)
+/// let half_line = HyperRectangle::new(vec![Axis::Above(0.0)])
+///                                .unwrap()
+///                                .with_transform(AxisTransform::Rational);
+/// let res = half_line.integrate(&mut GaussKronrod::new(100),
+///                               |x: Real| (1.0 + x * x).recip(), 1e-12, 1e-14)
+///                    .unwrap();
+/// assert!((res.results[0].value - ::std::f64::consts::FRAC_PI_2).abs() < 1e-12);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperRectangle<A> {
+    axes: Vec<Axis>,
+    transform: AxisTransform,
+    scale: Real,
+    marker: marker::PhantomData<fn(A)>,
+}
+
+impl<A: IntegrandInput> HyperRectangle<A> {
+    /// Creates a region from the range of each axis, using the `Tanh`
+    /// transform with a scale of 1 for infinite axes.
+    /// Returns `None` if there is not one axis for each dimension of the
+    /// integrand's input, `A`.
+    pub fn new(axes: Vec<Axis>) -> Option<Self> {
+        if axes.len() != A::input_size() {
+            return None;
+        }
+        Some(HyperRectangle {
+            axes,
+            transform: AxisTransform::Tanh,
+            scale: 1.0,
+            marker: marker::PhantomData,
+        })
+    }
+
+    /// Use a different transform for infinite axes.
+    /// (Default = `AxisTransform::Tanh`)
+    pub fn with_transform(self, transform: AxisTransform) -> Self {
+        HyperRectangle { transform, ..self }
+    }
+
+    /// Set the scale of infinite axes. (Default = 1)
+    pub fn with_scale(self, scale: Real) -> Self {
+        HyperRectangle { scale, ..self }
+    }
+
+    pub fn axes(&self) -> &[Axis] {
+        &self.axes[..]
+    }
+
+    pub fn transform(&self) -> AxisTransform {
+        self.transform
+    }
+
+    pub fn scale(&self) -> Real {
+        self.scale
+    }
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Domain for HyperRectangle<A> {
+    type Unit = A;
+    type Point = A;
+
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+        let mut jacobian = 1.0;
+        for ((axis, &t), x) in self.axes.iter().zip(unit.iter()).zip(point.iter_mut()) {
+            let (mapped, jac) = axis.map(t, self.transform, self.scale);
+            *x = mapped;
+            jacobian *= jac;
+        }
+        jacobian
+    }
+}
+
+/// Reads the coordinates of a point given as an integrand input type.
+fn coordinates<A: IntegrandOutput>(point: &A) -> Vec<Real> {
+    let mut coords = vec![0.0; point.output_size()];
+    point.into_args(&mut coords);
+    coords
+}
+
+/// Maps `angles`, in [0, 1], to a point at distance `r` from `center`, in
+/// as many dimensions as `point` has, using hyperspherical coordinates.
+/// Returns the Jacobian of the map, the area element of the sphere of
+/// radius `r`. `angles` must have one fewer dimension than `point`, which
+/// must have at least two.
+fn spherical(r: Real, angles: &[Real], center: &[Real], point: &mut [Real]) -> Real {
+    let last = point.len() - 1;
+    // The radius of the sphere which the remaining coordinates lie on
+    let mut radius = r;
+    let mut jacobian = 1.0;
+    for (i, &t) in angles.iter().enumerate() {
+        let range = if i + 1 == last { 2.0 * PI } else { PI };
+        let phi = range * t;
+        jacobian *= range * radius;
+        point[i] = center[i] + radius * phi.cos();
+        radius *= phi.sin();
+    }
+    point[last] = center[last] + radius;
+    jacobian
+}
+
+/// Maps a point of the unit hypercube to the spherical shell around
+/// `center` from radius `inner` to `outer`.
+fn shell(center: &[Real], inner: Real, outer: Real, unit: &[Real], point: &mut [Real]) -> Real {
+    if point.len() == 1 {
+        // The shell is the two intervals at either side of the center
+        let s = 2.0 * unit[0] - 1.0;
+        let r = inner + (outer - inner) * s.abs();
+        point[0] = center[0] + if s < 0.0 { -r } else { r };
+        return 2.0 * (outer - inner);
+    }
+    let r = inner + (outer - inner) * unit[0];
+    (outer - inner) * spherical(r, &unit[1..], center, point)
+}
+
+/// The simplex with the given vertices: a triangle in 2 dimensions, a
+/// tetrahedron in 3, and so on.
+///
+/// ```
+/// use integrators::Real2;
+/// use integrators::domain::{Domain, Simplex};
+/// # #[cfg(feature = "native")] {
+/// use integrators::native::Cubature;
+///
+/// let triangle = Simplex::new(&[(0.0, 0.0), (2.0, 0.0), (0.0, 1.0)]).unwrap();
+/// let res = triangle.integrate(&mut Cubature::new(10000), |(x, _): Real2| x, 1e-10, 0.0)
+///                   .unwrap();
+/// assert!((res.results[0].value - 2.0 / 3.0).abs() < 1e-10);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simplex<A> {
+    origin: Vec<Real>,
+    edges: Vec<Vec<Real>>,
+    scale: Real,
+    marker: marker::PhantomData<fn(A)>,
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Simplex<A> {
+    /// Creates the simplex with the given vertices. Returns `None` if there
+    /// is not one more vertex than the dimension of `A`, if any coordinate
+    /// is not finite, or if the simplex has no volume.
+    pub fn new(vertices: &[A]) -> Option<Self> {
+        let ndim = A::input_size();
+        if ndim == 0 || vertices.len() != ndim + 1 {
+            return None;
+        }
+        let origin = coordinates(&vertices[0]);
+        let edges: Vec<Vec<Real>> = vertices[1..].iter().map(|vertex| {
+            coordinates(vertex).iter().zip(origin.iter()).map(|(v, o)| v - o).collect()
+        }).collect();
+        if edges.iter().flat_map(|edge| edge.iter()).any(|x| !x.is_finite()) {
+            return None;
+        }
+
+        // The volume of the parallelepiped spanned by the edges, by Gaussian
+        // elimination with partial pivoting
+        let mut matrix = edges.clone();
+        let mut scale: Real = 1.0;
+        for col in 0..ndim {
+            let pivot = (col..ndim).max_by(|&i, &j| {
+                matrix[i][col].abs().partial_cmp(&matrix[j][col].abs())
+                                     .unwrap_or(Ordering::Equal)
+            }).unwrap();
+            matrix.swap(col, pivot);
+            let diagonal = matrix[col][col];
+            if diagonal == 0.0 || !diagonal.is_finite() {
+                return None;
+            }
+            scale *= diagonal;
+            let (done, rest) = matrix.split_at_mut(col + 1);
+            for row in rest.iter_mut() {
+                let factor = row[col] / diagonal;
+                for (x, &y) in row[col..].iter_mut().zip(done[col][col..].iter()) {
+                    *x -= factor * y;
+                }
+            }
+        }
+
+        Some(Simplex {
+            origin,
+            edges,
+            scale: scale.abs(),
+            marker: marker::PhantomData,
+        })
+    }
+
+    /// The volume of the simplex.
+    pub fn volume(&self) -> Real {
+        (1..self.edges.len() + 1).fold(self.scale, |volume, n| volume / n as Real)
+    }
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Domain for Simplex<A> {
+    type Unit = A;
+    type Point = A;
+
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+        // Each coordinate takes its fraction of what the previous ones left
+        // of the distance from the first vertex to the opposite face
+        point.copy_from_slice(&self.origin);
+        let mut rest = 1.0;
+        let mut jacobian = self.scale;
+        for (&t, edge) in unit.iter().zip(self.edges.iter()) {
+            let weight = rest * t;
+            for (x, &e) in point.iter_mut().zip(edge.iter()) {
+                *x += weight * e;
+            }
+            jacobian *= rest;
+            rest -= weight;
+        }
+        jacobian
+    }
+}
+
+/// The ball of the given center and radius: a disk in 2 dimensions, and an
+/// interval in 1.
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use integrators::Real3;
+/// use integrators::domain::{Ball, Domain};
+/// # #[cfg(feature = "native")] {
+/// use integrators::native::Cubature;
+///
+/// let ball = Ball::new((1.0, 2.0, 3.0), 2.0);
+/// let res = ball.integrate(&mut Cubature::new(100000), |_: Real3| 1.0, 1e-8, 0.0)
+///               .unwrap();
+/// assert!((res.results[0].value - 32.0 / 3.0 * PI).abs() < 1e-6);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ball<A> {
+    center: Vec<Real>,
+    radius: Real,
+    marker: marker::PhantomData<fn(A)>,
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Ball<A> {
+    pub fn new(center: A, radius: Real) -> Self {
+        Ball {
+            center: coordinates(&center),
+            radius,
+            marker: marker::PhantomData,
+        }
+    }
+
+    pub fn center(&self) -> A {
+        A::from_args(&self.center)
+    }
+
+    pub fn radius(&self) -> Real {
+        self.radius
+    }
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Domain for Ball<A> {
+    type Unit = A;
+    type Point = A;
+
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+        shell(&self.center, 0.0, self.radius, unit, point)
+    }
+}
+
+/// The region between two spheres of the same center: an annulus in 2
+/// dimensions, and a spherical shell in more.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annulus<A> {
+    center: Vec<Real>,
+    inner: Real,
+    outer: Real,
+    marker: marker::PhantomData<fn(A)>,
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Annulus<A> {
+    /// Creates the region around `center` between the radii `inner` and
+    /// `outer`, which should be the larger.
+    pub fn new(center: A, inner: Real, outer: Real) -> Self {
+        Annulus {
+            center: coordinates(&center),
+            inner,
+            outer,
+            marker: marker::PhantomData,
+        }
+    }
+
+    pub fn center(&self) -> A {
+        A::from_args(&self.center)
+    }
+
+    pub fn inner(&self) -> Real {
+        self.inner
+    }
+
+    pub fn outer(&self) -> Real {
+        self.outer
+    }
+}
+
+impl<A: IntegrandInput + IntegrandOutput> Domain for Annulus<A> {
+    type Unit = A;
+    type Point = A;
+
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+        shell(&self.center, self.inner, self.outer, unit, point)
+    }
+}
+
+/// The surface of a sphere in 3 dimensions, of the given center and radius.
+/// It is mapped from the unit square, by the polar and azimuthal angles.
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use integrators::Real3;
+/// use integrators::domain::{Domain, Sphere};
+/// # #[cfg(feature = "native")] {
+/// use integrators::native::Cubature;
+///
+/// // The mean of z^2 over the unit sphere is 1/3
+/// let sphere = Sphere::new((0.0, 0.0, 0.0), 1.0);
+/// let res = sphere.integrate(&mut Cubature::new(100000), |(_, _, z): Real3| z * z, 1e-10, 0.0)
+///                 .unwrap();
+/// assert!((res.results[0].value - 4.0 * PI / 3.0).abs() < 1e-8);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sphere {
+    center: Real3,
+    radius: Real,
+}
+
+impl Sphere {
+    pub fn new(center: Real3, radius: Real) -> Self {
+        Sphere { center, radius }
+    }
+
+    pub fn center(&self) -> Real3 {
+        self.center
+    }
+
+    pub fn radius(&self) -> Real {
+        self.radius
+    }
+}
+
+impl Domain for Sphere {
+    type Unit = Real2;
+    type Point = Real3;
+
+    fn map(&self, unit: &[Real], point: &mut [Real]) -> Real {
+        let (x, y, z) = self.center;
+        spherical(self.radius, unit, &[x, y, z], point)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "native")]
+mod test_domain {
+    use std::f64;
+    use std::f64::consts::PI;
+
+    use super::{Annulus, Axis, AxisTransform, Ball, Domain, HyperRectangle, Simplex, Sphere};
+    use ::{Real, Real2, Real3, Real4};
+    use ::native::{Cubature, QuasiMonteCarlo};
+
+    #[test]
+    fn test_axis_new() {
+        let inf = f64::INFINITY;
+        assert_eq!(Axis::new(1.0, -2.0), Some(Axis::Finite(1.0, -2.0)));
+        assert_eq!(Axis::new(1.0, inf), Some(Axis::Above(1.0)));
+        assert_eq!(Axis::new(-inf, -2.0), Some(Axis::Below(-2.0)));
+        assert_eq!(Axis::new(-inf, inf), Some(Axis::Whole));
+        assert_eq!(Axis::new(inf, 1.0), None);
+        assert_eq!(Axis::new(inf, inf), None);
+        assert_eq!(Axis::new(0.0, f64::NAN), None);
+        assert!(HyperRectangle::<Real2>::new(vec![Axis::Whole]).is_none());
+    }
+
+    #[test]
+    fn test_infinite_axes() {
+        // A Gaussian of mean (1, -2, 0.5) and width 0.5, over every kind of
+        // axis
+        let gaussian = |(x, y, z): Real3| {
+            (-((x - 1.0).powi(2) + (y + 2.0).powi(2) + (z - 0.5).powi(2)) / 0.5).exp()
+        };
+        let norm = (0.5 * PI).powf(1.5);
+        let domains = [
+            (vec![Axis::Whole, Axis::Whole, Axis::Whole], 1.0),
+            (vec![Axis::Above(1.0), Axis::Whole, Axis::Whole], 0.5),
+            (vec![Axis::Whole, Axis::Below(-2.0), Axis::Whole], 0.5),
+            (vec![Axis::Above(1.0), Axis::Below(-2.0), Axis::Finite(0.5, 10.0)], 0.125),
+        ];
+        for &transform in [AxisTransform::Tanh, AxisTransform::Rational].iter() {
+            for &(ref axes, fraction) in domains.iter() {
+                let domain = HyperRectangle::new(axes.clone()).unwrap()
+                                            .with_transform(transform)
+                                            .with_scale(0.5);
+                let res = domain.integrate(&mut Cubature::new(10000000), gaussian, 1e-7, 0.0)
+                                .unwrap();
+                let exp = fraction * norm;
+                assert!((res.results[0].value - exp).abs() < 1e-6 * exp,
+                        "{:?} {:?}: {} != {}", transform, axes, res.results[0].value, exp);
+            }
+        }
+    }
+
+    #[test]
+    fn test_algebraic_tails() {
+        // The Cauchy distribution, with vector outputs, and a Monte Carlo
+        // integrator, which may sample the ends of the unit interval
+        let cauchy = |(x, y): Real2| {
+            assert!(x.is_finite() && y.is_finite());
+            let p = 1.0 / (PI * PI * (1.0 + x * x) * (1.0 + y * y));
+            (p, p * (x > 0.0) as i32 as Real)
+        };
+        let domain = HyperRectangle::new(vec![Axis::Whole, Axis::Whole]).unwrap()
+                                    .with_transform(AxisTransform::Rational);
+        let res = domain.integrate(&mut QuasiMonteCarlo::new(), cauchy, 1e-3, 0.0)
+                        .unwrap();
+        assert!((res.results[0].value - 1.0).abs() < 1e-2);
+        assert!((res.results[1].value - 0.5).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_simplex() {
+        // The vertices are in clockwise order, which must not change the sign
+        let triangle = Simplex::new(&[(1.0, 1.0), (1.0, 3.0), (4.0, 1.0)]).unwrap();
+        assert!((triangle.volume() - 3.0).abs() < 1e-14);
+        let res = triangle.integrate(&mut Cubature::new(100000), |(x, y): Real2| (1.0, x, y),
+                                     1e-10, 0.0)
+                          .unwrap();
+        let centroid = [1.0, 2.0, 5.0 / 3.0];
+        for (res, &exp) in res.results.iter().zip(centroid.iter()) {
+            assert!((res.value - 3.0 * exp).abs() < 1e-9, "{} != {}", res.value, 3.0 * exp);
+        }
+
+        let tetrahedron = Simplex::new(&[(0.0, 0.0, 0.0), (0.0, 2.0, 0.0),
+                                         (1.0, 0.0, 0.0), (0.0, 0.0, 3.0)]).unwrap();
+        assert!((tetrahedron.volume() - 1.0).abs() < 1e-14);
+        let res = tetrahedron.integrate(&mut Cubature::new(100000), |(_, _, z): Real3| z,
+                                        1e-10, 0.0)
+                             .unwrap();
+        assert!((res.results[0].value - 0.75).abs() < 1e-9);
+
+        assert!(Simplex::new(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]).is_none());
+        assert!(Simplex::new(&[(0.0, 0.0), (1.0, 0.0)]).is_none());
+        assert!(Simplex::new(&[(0.0, 0.0), (1.0, f64::NAN), (2.0, 0.0)]).is_none());
+        assert!(Simplex::new(&[(f64::INFINITY, 0.0), (1.0, 1.0), (2.0, 0.0)]).is_none());
+        assert!(Simplex::new(&[(-f64::MAX, 0.0), (f64::MAX, 1.0), (0.0, 2.0)]).is_none());
+    }
+
+    #[test]
+    fn test_balls() {
+        let res = Ball::new(2.0, 3.0).integrate(&mut Cubature::new(1000), |x: Real| x, 1e-12, 0.0)
+                                     .unwrap();
+        assert!((res.results[0].value - 12.0).abs() < 1e-10);
+        let res = Ball::new((1.0, -1.0), 2.0).integrate(&mut Cubature::new(100000),
+                                                         |(x, y): Real2| (1.0, x * x + y * y),
+                                                         1e-10, 0.0)
+                                             .unwrap();
+        assert!((res.results[0].value - 4.0 * PI).abs() < 1e-9);
+        assert!((res.results[1].value - 16.0 * PI).abs() < 1e-8);
+        let res = Ball::new((0.0, 0.0, 0.0, 0.0), 2.0).integrate(&mut Cubature::new(1000000),
+                                                                  |_: Real4| 1.0, 1e-8, 0.0)
+                                                      .unwrap();
+        assert!((res.results[0].value - 8.0 * PI * PI).abs() < 1e-6);
+
+        let res = Annulus::new(0.0, 1.0, 3.0).integrate(&mut Cubature::new(1000), |_: Real| 1.0, 1e-12, 0.0)
+                                             .unwrap();
+        assert!((res.results[0].value - 4.0).abs() < 1e-10);
+        let res = Annulus::new((0.0, 0.0), 1.0, 2.0).integrate(&mut Cubature::new(100000),
+                                                                |(x, y): Real2| x * x + y * y,
+                                                                1e-10, 0.0)
+                                                    .unwrap();
+        assert!((res.results[0].value - 7.5 * PI).abs() < 1e-9);
+
+        let res = Sphere::new((1.0, 2.0, 3.0), 2.0).integrate(&mut Cubature::new(100000),
+                                                               |(_, _, z): Real3| z,
+                                                               1e-10, 0.0)
+                                                   .unwrap();
+        assert!((res.results[0].value - 48.0 * PI).abs() < 1e-8);
+    }
+}
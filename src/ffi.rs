@@ -12,17 +12,54 @@ use std::panic;
 use ::traits::{IntegrandInput, IntegrandOutput};
 use ::Real;
 
-pub struct LandingPad<A, B, F: FnMut(A) -> B> {
+pub struct LandingPad<A, B, F> {
     err: Option<Box<Any + Send + 'static>>,
     fun: F,
     a: PhantomData<A>,
     b: PhantomData<B>,
 }
 
+impl<A, B, F> LandingPad<A, B, F> {
+    /// Runs `call` on the wrapped integrand, catching any panic. Behaves as
+    /// described for `try_call()`.
+    fn catch<C>(&mut self, call: C) -> Result<(), &(dyn Any + Send + 'static)>
+        where C: FnOnce(&mut F) {
+        if self.err.is_some() {
+            Err(self.err.as_ref().expect("just said it is some"))
+        } else {
+            let res = {
+                let fun = &mut self.fun;
+                panic::catch_unwind(panic::AssertUnwindSafe(move || call(fun)))
+            };
+            match res {
+                Ok(()) => Ok(()),
+                Err(err) => {
+                    self.err = Some(err);
+                    Err(self.err.as_ref().expect("just set to Some(..)"))
+                }
+            }
+        }
+    }
+
+    pub fn maybe_resume_unwind(self) {
+        if self.err.is_some() {
+            self.resume_unwind()
+        }
+    }
+
+    pub fn finish(self) -> Option<Box<dyn Any + Send + 'static>> {
+        self.err
+    }
+
+    fn resume_unwind(self) -> ! {
+        panic::resume_unwind(self.err.expect("trying to resume unwind"))
+    }
+}
+
 impl<A: IntegrandInput, B: IntegrandOutput, F: FnMut(A) -> B> LandingPad<A, B, F> {
     pub fn new(fun: F) -> Self {
         LandingPad {
-            err: None, fun: fun,
+            err: None, fun,
             a: PhantomData, b: PhantomData,
         }
     }
@@ -37,44 +74,69 @@ impl<A: IntegrandInput, B: IntegrandOutput, F: FnMut(A) -> B> LandingPad<A, B, F
     /// panic. In other words, the integrand will only be allowed to panic
     /// once.
     pub fn try_call(&mut self, args: &[Real], output: &mut [Real]) -> Result<(), &(Any + Send + 'static)> {
-        if self.err.is_some() {
-            Err(self.err.as_ref().expect("just said it is some"))
-        } else {
-            // FIXME: Is there a better way to do this? This took some hassle to
-            // figure out
-            let res = {
-                let mut borself = panic::AssertUnwindSafe(&mut *self);
-                let mut output_buf = panic::AssertUnwindSafe(&mut *output);
-                panic::catch_unwind(move || {
-                    let lp: &mut LandingPad<A, B, F> = &mut borself;
-                    (lp.fun)(A::from_args(args)).into_args(&mut *output_buf)
-                })
-            };
-            match res {
-                Ok(()) => Ok(()),
-                Err(err) => {
-                    self.err = Some(err);
-                    Err(self.err.as_ref().expect("just set to Some(..)"))
-                }
-            }
-        }
+        self.catch(|fun| fun(A::from_args(args)).into_args(output))
     }
 
     pub fn raw_call(&mut self, args: &[Real]) -> B {
         (self.fun)(A::from_args(args))
     }
+}
 
-    pub fn maybe_resume_unwind(self) {
-        if self.err.is_some() {
-            self.resume_unwind()
+impl<A: IntegrandInput, B: IntegrandOutput, F: FnMut(&[A]) -> Vec<B>> LandingPad<A, B, F> {
+    /// Wraps an integrand which takes a slice of points, and returns one
+    /// output per point.
+    pub fn new_vectorized(fun: F) -> Self {
+        LandingPad {
+            err: None, fun,
+            a: PhantomData, b: PhantomData,
         }
     }
 
-    pub fn finish(self) -> Option<Box<Any + Send + 'static>> {
-        self.err
+    /// The vectorized equivalent of `try_call()`, for integrands which
+    /// evaluate many points at once. `args` holds `npoints` consecutive
+    /// points of `A::input_size()` values each, and `output` has room for
+    /// `npoints` consecutive outputs of equal size.
+    ///
+    /// If the integrand returns a different number of outputs than the
+    /// number of points it was given, that is caught as a panic.
+    pub fn try_call_vectorized(&mut self, args: &[Real], output: &mut [Real]) -> Result<(), &(Any + Send + 'static)> {
+        self.catch(|fun| {
+            let points = args.chunks(A::input_size())
+                             .map(A::from_args)
+                             .collect::<Vec<A>>();
+            let results = fun(&points[..]);
+            if results.len() != points.len() {
+                panic!("Vectorized integrand returned {} outputs for {} points",
+                       results.len(), points.len());
+            }
+            let ncomp = output.len() / points.len();
+            for (res, out) in results.iter().zip(output.chunks_mut(ncomp)) {
+                res.into_args(out)
+            }
+        })
     }
 
-    fn resume_unwind(self) -> ! {
-        panic::resume_unwind(self.err.expect("trying to resume unwind"))
+    pub fn raw_call_vectorized(&mut self, args: &[Real]) -> Vec<B> {
+        let points = args.chunks(A::input_size())
+                         .map(A::from_args)
+                         .collect::<Vec<A>>();
+        (self.fun)(&points[..])
+    }
+}
+
+impl<F: FnMut(&[Real], &mut [Real])> LandingPad<(), (), F> {
+    /// Wraps an integrand which reads its inputs from one slice and writes
+    /// its outputs to another, so their sizes need only be known at runtime.
+    /// See `traits::DynIntegrator`.
+    pub fn new_dyn(fun: F) -> Self {
+        LandingPad {
+            err: None, fun,
+            a: PhantomData, b: PhantomData,
+        }
+    }
+
+    /// The equivalent of `try_call()` for integrands wrapped by `new_dyn()`.
+    pub fn try_call_dyn(&mut self, args: &[Real], output: &mut [Real]) -> Result<(), &(dyn Any + Send + 'static)> {
+        self.catch(|fun| fun(args, output))
     }
 }
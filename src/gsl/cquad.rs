@@ -0,0 +1,145 @@
+use std::convert::Into;
+use std::fmt;
+
+use ::bindings;
+use ::{Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLNevalResult};
+
+struct CQUADWorkspace {
+    nintervals: usize,
+    wkspc: *mut bindings::gsl_integration_cquad_workspace
+}
+
+impl fmt::Debug for CQUADWorkspace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("CQUADWorkspace")
+           .field("nintervals", &self.nintervals)
+           .finish()
+    }
+}
+
+impl Clone for CQUADWorkspace {
+    fn clone(&self) -> Self {
+        CQUADWorkspace::new(self.nintervals)
+    }
+}
+
+impl CQUADWorkspace {
+    fn new(n: usize) -> Self {
+        assert!(n >= 3, "CQUAD workspace needs at least 3 intervals");
+        CQUADWorkspace {
+            wkspc: unsafe {
+                bindings::gsl_integration_cquad_workspace_alloc(n)
+            },
+            nintervals: n
+        }
+    }
+}
+
+impl Drop for CQUADWorkspace {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_integration_cquad_workspace_free(self.wkspc)
+        }
+    }
+}
+
+/// Doubly-adaptive integration. CQUAD applies Clenshaw-Curtis rules of
+/// increasing degree to each subinterval, bisecting only where increasing
+/// the degree does not help. Points where the integrand is not finite are
+/// dropped from the rules, so it is much more robust than `QAGS` for
+/// integrands with NaNs, infinities, or discontinuities, at the cost of
+/// more evaluations for well-behaved ones.
+///
+/// Its results include the number of integrand evaluations used.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#cquad-doubly-adaptive-integration).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+///
+/// // Infinite at x = 0
+/// let res = gsl::CQUAD::new(100)
+///                      .integrate(|x: Real| x.sqrt().recip(), 1e-8, 1e-10)
+///                      .unwrap();
+/// assert!((res.value - 2.0).abs() < 1e-7);
+/// assert!(res.neval > 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CQUAD {
+    range_low: Real,
+    range_high: Real,
+    wkspc: CQUADWorkspace,
+}
+
+impl CQUAD {
+    /// Creates a new CQUAD with enough memory for `nintervals` subintervals.
+    /// This will integrate over [0, 1]. To change the integration bounds, see
+    /// `with_range`. GSL suggests 100 subintervals is enough for most
+    /// integrands.
+    ///
+    /// # Panics
+    /// If `nintervals` is less than 3.
+    pub fn new(nintervals: usize) -> Self {
+        CQUAD {
+            range_low: 0.0,
+            range_high: 1.0,
+            wkspc: CQUADWorkspace::new(nintervals)
+        }
+    }
+
+    /// Discards the old workspace and allocates a new one with enough memory
+    /// for `nintervals` subintervals.
+    ///
+    /// # Panics
+    /// If `nintervals` is less than 3.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        CQUAD {
+            wkspc: CQUADWorkspace::new(nintervals),
+            ..self
+        }
+    }
+
+    /// Use a different integration range. (Default = [0, 1])
+    pub fn with_range(self, range_low: Real, range_high: Real) -> Self {
+        CQUAD { range_low, range_high, ..self }
+    }
+}
+
+impl Integrator for CQUAD {
+    type Success = GSLNevalResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let mut value: Real = 0.0;
+        let mut error: Real = 0.0;
+        let mut neval: usize = 0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let mut gslfn = make_gsl_function(&mut lp, self.range_low, self.range_high)?;
+            bindings::gsl_integration_cquad(&mut gslfn.function,
+                                            self.range_low, self.range_high,
+                                            epsabs, epsrel,
+                                            self.wkspc.wkspc,
+                                            &mut value,
+                                            &mut error,
+                                            &mut neval)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(GSLNevalResult {
+                value, error, neval
+            })
+        }
+    }
+}
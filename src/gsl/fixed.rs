@@ -0,0 +1,281 @@
+use std::{fmt, slice};
+
+use ::bindings;
+use ::{IntegrationResult, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLErrorCode, GSLIntegrationError, GSLResult};
+
+/// The weight function, and thus the family of orthogonal polynomials, of a
+/// `FixedQuadrature` rule. Each variant carries the parameters of its
+/// weight function, which also determine the integration range.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#fixed-point-quadratures)
+/// for the allowed values of each parameter.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FixedRuleType {
+    /// Gauss-Legendre: weight `1` over `[a, b]`.
+    Legendre { a: Real, b: Real },
+    /// Gauss-Chebyshev (first kind): weight `1 / sqrt((b - x) (x - a))` over
+    /// `[a, b]`.
+    Chebyshev { a: Real, b: Real },
+    /// Gauss-Gegenbauer: weight `((b - x) (x - a))^alpha` over `[a, b]`.
+    Gegenbauer { a: Real, b: Real, alpha: Real },
+    /// Gauss-Jacobi: weight `(b - x)^alpha (x - a)^beta` over `[a, b]`.
+    Jacobi { a: Real, b: Real, alpha: Real, beta: Real },
+    /// Generalized Gauss-Laguerre: weight `(x - a)^alpha exp(-b (x - a))`
+    /// over `[a, +inf)`.
+    Laguerre { a: Real, b: Real, alpha: Real },
+    /// Generalized Gauss-Hermite: weight `|x - a|^alpha exp(-b (x - a)^2)`
+    /// over `(-inf, +inf)`.
+    Hermite { a: Real, b: Real, alpha: Real },
+    /// Exponential: weight `|x - (a + b) / 2|^alpha` over `[a, b]`.
+    Exponential { a: Real, b: Real, alpha: Real },
+    /// Rational: weight `(x - a)^alpha (x + b)^beta` over `[a, +inf)`.
+    Rational { a: Real, b: Real, alpha: Real, beta: Real },
+    /// Gauss-Chebyshev (second kind): weight `sqrt((b - x) (x - a))` over
+    /// `[a, b]`.
+    Chebyshev2 { a: Real, b: Real },
+}
+
+impl FixedRuleType {
+    /// The GSL rule type, and its parameters `(a, b, alpha, beta)`.
+    fn raw(&self) -> (*const bindings::gsl_integration_fixed_type, Real, Real, Real, Real) {
+        use self::FixedRuleType::*;
+        unsafe {
+            match *self {
+                Legendre { a, b } =>
+                    (bindings::gsl_integration_fixed_legendre, a, b, 0.0, 0.0),
+                Chebyshev { a, b } =>
+                    (bindings::gsl_integration_fixed_chebyshev, a, b, 0.0, 0.0),
+                Gegenbauer { a, b, alpha } =>
+                    (bindings::gsl_integration_fixed_gegenbauer, a, b, alpha, 0.0),
+                Jacobi { a, b, alpha, beta } =>
+                    (bindings::gsl_integration_fixed_jacobi, a, b, alpha, beta),
+                Laguerre { a, b, alpha } =>
+                    (bindings::gsl_integration_fixed_laguerre, a, b, alpha, 0.0),
+                Hermite { a, b, alpha } =>
+                    (bindings::gsl_integration_fixed_hermite, a, b, alpha, 0.0),
+                Exponential { a, b, alpha } =>
+                    (bindings::gsl_integration_fixed_exponential, a, b, alpha, 0.0),
+                Rational { a, b, alpha, beta } =>
+                    (bindings::gsl_integration_fixed_rational, a, b, alpha, beta),
+                Chebyshev2 { a, b } =>
+                    (bindings::gsl_integration_fixed_chebyshev2, a, b, 0.0, 0.0),
+            }
+        }
+    }
+
+    /// Checks the parameters as GSL would, so that we can report which one
+    /// is invalid.
+    fn verify(&self, n: usize) -> GSLResult<()> {
+        use self::FixedRuleType::*;
+        let invalid = |descr| Err(GSLIntegrationError::InvalidParameter(descr));
+        let (a, b, alpha, beta) = match *self {
+            Legendre { a, b } | Chebyshev { a, b } | Chebyshev2 { a, b } =>
+                (a, b, 0.0, 0.0),
+            Gegenbauer { a, b, alpha } | Laguerre { a, b, alpha }
+                | Hermite { a, b, alpha } | Exponential { a, b, alpha } =>
+                (a, b, alpha, 0.0),
+            Jacobi { a, b, alpha, beta } | Rational { a, b, alpha, beta } =>
+                (a, b, alpha, beta),
+        };
+
+        if n == 0 {
+            return invalid("fixed quadrature needs at least one node");
+        }
+        if !(alpha > -1.0) {
+            return invalid("fixed quadrature alpha must be greater than -1");
+        }
+        match *self {
+            Laguerre { .. } | Hermite { .. } => if !(b > 0.0) {
+                return invalid("fixed quadrature b must be positive");
+            },
+            Rational { .. } => {
+                if !(alpha + beta + 2.0 * (n as Real) < 0.0) {
+                    return invalid("rational quadrature needs alpha + beta + 2n < 0");
+                }
+                if !(a + b > 0.0) {
+                    return invalid("rational quadrature needs a + b > 0");
+                }
+            },
+            _ => if !((b - a).abs() > ::std::f64::EPSILON) {
+                return invalid("fixed quadrature range is too small");
+            },
+        }
+        if let Jacobi { .. } = *self {
+            if !(beta > -1.0) {
+                return invalid("fixed quadrature beta must be greater than -1");
+            }
+        }
+        Ok(())
+    }
+}
+
+struct FixedWorkspace {
+    rule: FixedRuleType,
+    n: usize,
+    wkspc: *mut bindings::gsl_integration_fixed_workspace
+}
+
+impl fmt::Debug for FixedWorkspace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FixedWorkspace")
+           .field("rule", &self.rule)
+           .field("n", &self.n)
+           .finish()
+    }
+}
+
+impl Clone for FixedWorkspace {
+    fn clone(&self) -> Self {
+        FixedWorkspace::new(self.rule, self.n)
+            .expect("parameters were checked when first allocated")
+    }
+}
+
+impl FixedWorkspace {
+    fn new(rule: FixedRuleType, n: usize) -> GSLResult<Self> {
+        rule.verify(n)?;
+        unsafe { bindings::gsl_set_error_handler_off() };
+        let (ty, a, b, alpha, beta) = rule.raw();
+        let wkspc = unsafe {
+            bindings::gsl_integration_fixed_alloc(ty, n, a, b, alpha, beta)
+        };
+        if wkspc.is_null() {
+            Err(GSLIntegrationError::GSLError(GSLErrorCode::Domain))
+        } else {
+            Ok(FixedWorkspace { rule, n, wkspc })
+        }
+    }
+
+    fn nodes(&self) -> &[Real] {
+        unsafe {
+            slice::from_raw_parts(bindings::gsl_integration_fixed_nodes(self.wkspc),
+                                  self.n)
+        }
+    }
+
+    fn weights(&self) -> &[Real] {
+        unsafe {
+            slice::from_raw_parts(bindings::gsl_integration_fixed_weights(self.wkspc),
+                                  self.n)
+        }
+    }
+}
+
+impl Drop for FixedWorkspace {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_integration_fixed_free(self.wkspc)
+        }
+    }
+}
+
+/// Fixed-order Gaussian quadrature. Approximates the integral of
+/// `w(x) f(x)`, where `w` is the weight function of the chosen
+/// `FixedRuleType`, by a weighted sum of `f` at `n` nodes. The rule is exact
+/// when `f` is a polynomial of degree up to `2n - 1`.
+///
+/// The nodes and weights are computed once, when the rule is created, and
+/// reused by every integration. There is
+/// no error estimate: the reported `error` is always NaN, and `epsrel` and
+/// `epsabs` are ignored.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#fixed-point-quadratures).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+/// use integrators::gsl::FixedRuleType;
+///
+/// // \int_0^\infty x^3 e^{-x} dx = 3!
+/// let mut laguerre = gsl::FixedQuadrature::new(
+///     FixedRuleType::Laguerre { a: 0.0, b: 1.0, alpha: 0.0 }, 4).unwrap();
+/// assert_eq!(laguerre.nodes().len(), 4);
+///
+/// let res = laguerre.integrate(|x: Real| x.powi(3), 0.0, 0.0)
+///                   .unwrap();
+/// assert!((res.value - 6.0).abs() < 1e-10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FixedQuadrature {
+    wkspc: FixedWorkspace,
+}
+
+impl FixedQuadrature {
+    /// Computes the `n` nodes and weights of the given rule.
+    /// Returns `Err(GSLIntegrationError::InvalidParameter(..))` if `n` is 0,
+    /// or the parameters of the rule are outside of their allowed range.
+    pub fn new(rule: FixedRuleType, n: usize) -> GSLResult<Self> {
+        Ok(FixedQuadrature {
+            wkspc: FixedWorkspace::new(rule, n)?
+        })
+    }
+
+    /// Use a different rule, keeping the same number of nodes. As with
+    /// `FixedQuadrature::new()`, returns an error if its parameters are
+    /// invalid.
+    pub fn with_rule(self, rule: FixedRuleType) -> GSLResult<Self> {
+        FixedQuadrature::new(rule, self.wkspc.n)
+    }
+
+    /// Use a different number of nodes, keeping the same rule. As with
+    /// `FixedQuadrature::new()`, returns an error if `n` is invalid.
+    pub fn with_n(self, n: usize) -> GSLResult<Self> {
+        FixedQuadrature::new(self.wkspc.rule, n)
+    }
+
+    pub fn rule(&self) -> FixedRuleType {
+        self.wkspc.rule
+    }
+
+    pub fn n(&self) -> usize {
+        self.wkspc.n
+    }
+
+    /// The points at which the integrand is evaluated.
+    pub fn nodes(&self) -> &[Real] {
+        self.wkspc.nodes()
+    }
+
+    /// The weights of the integrand at each of the `nodes()`.
+    pub fn weights(&self) -> &[Real] {
+        self.wkspc.weights()
+    }
+}
+
+impl Integrator for FixedQuadrature {
+    type Success = IntegrationResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, _epsrel: Real, _epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        // Checking the output dimension evaluates the integrand at the
+        // midpoint of the given range, so use a node which is known to be
+        // finite, rather than the (possibly infinite) ends of the range.
+        let probe = self.nodes()[0];
+        let mut value: Real = 0.0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let gslfn = make_gsl_function(&mut lp, probe, probe)?;
+            bindings::gsl_integration_fixed(&gslfn.function,
+                                            &mut value,
+                                            self.wkspc.wkspc)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(IntegrationResult {
+                value,
+                error: ::std::f64::NAN,
+            })
+        }
+    }
+}
@@ -5,10 +5,18 @@
 //! module, are gated with the `gsl` feature. So, if you don't want to use
 //! these wrappers, or don't have GSL installed, just turn off that feature.
 //!
-//! Note that GSL's integration routines can only support integration over
-//! one dimension; if you need to integrate over multiple dimensions, you can
-//! nest calls to integrators, or (preferrably) use another library such as
-//! Cuba.
+//! Note that GSL's quadrature routines can only support integration over
+//! one dimension. For multiple dimensions, GSL's Monte Carlo integrators are
+//! wrapped by `MontePlain`, `MonteMiser` and `MonteVegas`, which integrate
+//! over a box given by a `MonteRange`, and smooth integrands can be
+//! integrated over such a box with the tensor-product Gauss-Legendre rule
+//! of `GLFixedND`. Otherwise, you can nest these integrators with
+//! `iterated::Iterated`, which also allows the limits of inner dimensions to
+//! depend on the outer ones, or use another library such as Cuba.
+//!
+//! The one-dimensional routines also implement `DynIntegrator`, for
+//! integrands whose sizes are only known at runtime, which must then have
+//! one input and one output.
 //!
 //! ```rust
 //! use integrators::{Integrator, Real};
@@ -33,15 +41,16 @@
 //! }
 //! ```
 
-use std::{error, fmt, marker, mem};
+use std::{error, fmt, marker, mem, vec};
 use std::convert::{From, Into};
 use std::ffi::CStr;
 use std::os::raw::{c_void, c_int};
 
 use super::bindings;
 use super::ffi::LandingPad;
-use super::traits::{IntegrandInput, IntegrandOutput};
-use super::Real;
+use super::traits::{DynIntegrator, IntegrandInput, IntegrandOutput, IntegrationResults,
+                    Integrator};
+use super::{IntegrationResult, IntegrationResultIter, Real};
 
 #[cfg(test)]
 mod test;
@@ -61,6 +70,79 @@ pub use self::qagp::QAGP;
 mod qagi;
 pub use self::qagi::{QAGI, QAGIU, QAGIL};
 
+mod qawo;
+pub use self::qawo::{QAWO, QAWOWeight};
+
+mod qawf;
+pub use self::qawf::QAWF;
+
+mod qaws;
+pub use self::qaws::{QAWS, QAWSWeight};
+
+mod qawc;
+pub use self::qawc::QAWC;
+
+mod cquad;
+pub use self::cquad::CQUAD;
+
+mod romberg;
+pub use self::romberg::{Romberg, ROMBERG_MAX_ITER};
+
+mod fixed;
+pub use self::fixed::{FixedQuadrature, FixedRuleType};
+
+mod monte;
+pub use self::monte::MonteRange;
+
+mod monte_plain;
+pub use self::monte_plain::MontePlain;
+
+mod monte_miser;
+pub use self::monte_miser::MonteMiser;
+
+mod monte_vegas;
+pub use self::monte_vegas::{MonteVegas, GSLVegasMode, GSLVegasResult};
+
+mod glfixed_nd;
+pub use self::glfixed_nd::GLFixedND;
+
+/// Integrates `fun`, whose `ndim` inputs and `ncomp` outputs are only known
+/// at runtime, with a one-dimensional integrator, so both must be 1.
+fn integrate_dyn_1d<I>(integrator: &mut I, ndim: usize, ncomp: usize,
+                       fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                       epsrel: Real, epsabs: Real) -> Result<I::Success, GSLIntegrationError>
+    where I: Integrator<Failure = GSLIntegrationError>
+{
+    if ndim != 1 {
+        return Err(GSLIntegrationError::InvalidInputDim(ndim));
+    }
+    if ncomp != 1 {
+        return Err(GSLIntegrationError::InvalidOutputDim(ncomp));
+    }
+    integrator.integrate(|x: Real| {
+        let mut output: [Real; 1] = [0.0];
+        fun(&[x], &mut output);
+        output[0]
+    }, epsrel, epsabs)
+}
+
+macro_rules! impl_dyn_integrator_1d {
+    ($($ty:ty),*) => {
+        $(
+            impl DynIntegrator for $ty {
+                fn integrate_dyn(&mut self, ndim: usize, ncomp: usize,
+                                 fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                                 epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure> {
+                    integrate_dyn_1d(self, ndim, ncomp, fun, epsrel, epsabs)
+                }
+            }
+        )*
+    }
+}
+
+impl_dyn_integrator_1d!(QNG, QAG, QAGS, QAGP, QAGI, QAGIU, QAGIL, QAWO, QAWF, QAWS, QAWC,
+                        CQUAD, Romberg, FixedQuadrature);
+
 unsafe extern "C"
 fn gsl_integrand_fn<A, B, F>(x: Real, params: *mut c_void) -> Real
     where A: IntegrandInput,
@@ -132,6 +214,9 @@ pub enum GSLErrorCode {
     Diverge,
     /// Domain error on input arguments
     Domain,
+    /// The table of moments used by QAWO or QAWF has too few levels for the
+    /// required number of subdivisions
+    Table,
     /// Non-integration-specific GSL error code
     Other(c_int)
 }
@@ -176,6 +261,7 @@ impl From<c_int> for GSLErrorCode {
             bindings::GSL_ESING => Sing,
             bindings::GSL_EDIVERGE => Diverge,
             bindings::GSL_EDOM => Domain,
+            bindings::GSL_ETABLE => Table,
             _ => Other(n)
         }
     }
@@ -190,6 +276,7 @@ impl Into<c_int> for GSLErrorCode {
             Sing => bindings::GSL_ESING,
             Diverge => bindings::GSL_EDIVERGE,
             Domain => bindings::GSL_EDOM,
+            Table => bindings::GSL_ETABLE,
             Other(n) => n,
         }
     }
@@ -199,6 +286,9 @@ impl Into<c_int> for GSLErrorCode {
 pub enum GSLIntegrationError {
     InvalidInputDim(usize),
     InvalidOutputDim(usize),
+    /// A parameter of the integrator is invalid. The description says which,
+    /// and why.
+    InvalidParameter(&'static str),
     GSLError(GSLErrorCode),
 }
 
@@ -208,6 +298,7 @@ impl fmt::Display for GSLIntegrationError {
         match &self {
             &InvalidInputDim(n) => write!(fmt, "(GSL) Invalid input dim: {}", n),
             &InvalidOutputDim(n) => write!(fmt, "(GSL) Invalid output dim: {}", n),
+            &InvalidParameter(descr) => write!(fmt, "(GSL) Invalid parameter: {}", descr),
             &GSLError(err) => write!(fmt, "{}", err)
         }
     }
@@ -230,6 +321,42 @@ pub struct GSLIntegrationResult {
     pub error: Real
 }
 
+/// The result of a GSL routine which also reports how many times it
+/// evaluated the integrand.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GSLNevalResult {
+    pub value: Real,
+    pub error: Real,
+    /// The number of integrand evaluations used.
+    pub neval: usize,
+}
+
+impl IntegrationResults for GSLNevalResult {
+    type Iterator = IntegrationResultIter;
+    fn results(self) -> Self::Iterator {
+        IntegrationResult {
+            value: self.value,
+            error: self.error,
+        }.results()
+    }
+}
+
+/// The results of a GSL integrator which supports vector outputs, with one
+/// result for each component of the integrand's output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GSLIntegrationResults {
+    /// The number of integrand evaluations used.
+    pub neval: usize,
+    pub results: Vec<IntegrationResult>,
+}
+
+impl IntegrationResults for GSLIntegrationResults {
+    type Iterator = vec::IntoIter<IntegrationResult>;
+    fn results(self) -> Self::Iterator {
+        self.results.into_iter()
+    }
+}
+
 struct GSLIntegrationWorkspace {
     pub(crate) nintervals: usize,
     wkspc: *mut bindings::gsl_integration_workspace
@@ -0,0 +1,215 @@
+use std::{fmt, marker, mem, slice};
+use std::cell::Cell;
+use std::os::raw::{c_ulong, c_void};
+
+use ::bindings;
+use ::Real;
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{GSLErrorCode, GSLIntegrationError, GSLNevalResult, GSLResult};
+
+/// The region integrated over by GSL's multidimensional integrators: the
+/// box between the lower corner `xl` and the upper corner `xu`.
+///
+/// ```
+/// use integrators::gsl::MonteRange;
+///
+/// let range = MonteRange::new((0.0, -1.0), (2.0, 1.0)).unwrap();
+/// assert_eq!(range.ndim(), 2);
+/// assert_eq!(range.volume(), 4.0);
+/// assert!(MonteRange::new((0.0, 1.0), (2.0, 1.0)).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteRange {
+    xl: Vec<Real>,
+    xu: Vec<Real>,
+}
+
+impl MonteRange {
+    /// The box between `xl` and `xu`, which give the bounds of each
+    /// dimension of the integrand's input.
+    /// Returns `Err(GSLIntegrationError::InvalidParameter(..))` unless `xl`
+    /// is below `xu` in every dimension.
+    pub fn new<A: IntegrandOutput>(xl: A, xu: A) -> GSLResult<Self> {
+        let mut low = vec![0.0; xl.output_size()];
+        let mut high = vec![0.0; xu.output_size()];
+        xl.into_args(&mut low);
+        xu.into_args(&mut high);
+        if low.len() != high.len() || low.is_empty() {
+            return Err(GSLIntegrationError::InvalidParameter(
+                "the corners of a range must have the same, nonzero dimension"));
+        }
+        if !low.iter().zip(high.iter()).all(|(l, h)| l < h) {
+            return Err(GSLIntegrationError::InvalidParameter(
+                "the lower corner of a range must be below the upper corner"));
+        }
+        Ok(MonteRange { xl: low, xu: high })
+    }
+
+    /// The unit hypercube of `ndim` dimensions.
+    pub(super) fn unit(ndim: usize) -> Self {
+        MonteRange {
+            xl: vec![0.0; ndim],
+            xu: vec![1.0; ndim],
+        }
+    }
+
+    pub fn ndim(&self) -> usize {
+        self.xl.len()
+    }
+
+    /// The lower corner of the box.
+    pub fn xl(&self) -> &[Real] {
+        &self.xl[..]
+    }
+
+    /// The upper corner of the box.
+    pub fn xu(&self) -> &[Real] {
+        &self.xu[..]
+    }
+
+    pub fn volume(&self) -> Real {
+        self.xl.iter().zip(self.xu.iter()).map(|(l, h)| h - l).product()
+    }
+
+    fn midpoint(&self) -> Vec<Real> {
+        self.xl.iter().zip(self.xu.iter()).map(|(l, h)| (l + h) / 2.0).collect()
+    }
+}
+
+/// A GSL random number generator, of GSL's default type (MT19937).
+pub(super) struct GSLRng {
+    pub(super) rng: *mut bindings::gsl_rng,
+    seed: usize,
+}
+
+impl fmt::Debug for GSLRng {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("GSLRng")
+           .field("seed", &self.seed)
+           .finish()
+    }
+}
+
+impl Clone for GSLRng {
+    /// Copies the generator, in its current state.
+    fn clone(&self) -> Self {
+        let rng = unsafe { bindings::gsl_rng_clone(self.rng) };
+        assert!(!rng.is_null(), "could not allocate GSL random number generator");
+        GSLRng { rng, seed: self.seed }
+    }
+}
+
+impl GSLRng {
+    pub(super) fn new(seed: usize) -> Self {
+        let rng = unsafe { bindings::gsl_rng_alloc(bindings::gsl_rng_default) };
+        assert!(!rng.is_null(), "could not allocate GSL random number generator");
+        unsafe { bindings::gsl_rng_set(rng, seed as c_ulong) };
+        GSLRng { rng, seed }
+    }
+}
+
+impl Drop for GSLRng {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_rng_free(self.rng)
+        }
+    }
+}
+
+unsafe extern "C"
+fn gsl_monte_integrand_fn<A, B, F>(x: *mut Real, dim: usize, params: *mut c_void) -> Real
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    let fnptr = params as *mut LandingPad<A, B, F>;
+    let fun: &mut LandingPad<A, B, F> = &mut *fnptr;
+
+    let mut output: [Real; 1] = [0.0];
+    match fun.try_call(slice::from_raw_parts(x, dim), &mut output) {
+        Ok(_) => output[0],
+        Err(_) => 0.0,
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct GSLMonteFunction<'a> {
+    pub(super) function: bindings::gsl_monte_function,
+    lifetime: marker::PhantomData<&'a ()>
+}
+
+pub(super) fn make_monte_function<'a, A, B, F>(fun: &'a mut LandingPad<A, B, F>, range: &MonteRange)
+        -> GSLResult<GSLMonteFunction<'a>>
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    // Disable the default error handler so we can handle GSL errors in Rust
+    unsafe { bindings::gsl_set_error_handler_off() };
+
+    if range.ndim() != A::input_size() {
+        return Err(GSLIntegrationError::InvalidInputDim(A::input_size()));
+    }
+    let output_size = fun.raw_call(&range.midpoint()).output_size();
+    if output_size != 1 {
+        Err(GSLIntegrationError::InvalidOutputDim(output_size))
+    } else {
+        Ok(GSLMonteFunction {
+            function: bindings::gsl_monte_function {
+                f: Some(gsl_monte_integrand_fn::<A, B, F>),
+                dim: A::input_size(),
+                params: unsafe { mem::transmute(fun) }
+            },
+            lifetime: marker::PhantomData
+        })
+    }
+}
+
+/// Wraps `fun` to count its evaluations in `neval`.
+pub(super) fn counted<'a, A, B, F>(neval: &'a Cell<usize>, mut fun: F) -> impl FnMut(A) -> B + 'a
+    where F: FnMut(A) -> B + 'a
+{
+    move |x| {
+        neval.set(neval.get() + 1);
+        fun(x)
+    }
+}
+
+pub(super) fn meets_tolerance(value: Real, error: Real, epsrel: Real, epsabs: Real) -> bool {
+    error <= epsabs.max(epsrel * value.abs())
+}
+
+/// Repeats independent `run`s of a Monte Carlo routine, which each return
+/// an estimate and its error, until the mean of the estimates meets the
+/// tolerance. Fails with `GSLErrorCode::MaxIter` if it has not after
+/// another run would take more than `maxcalls` evaluations, as counted by
+/// `neval`.
+pub(super) fn integrate_runs<R>(mut run: R, neval: &Cell<usize>, maxcalls: usize, epsrel: Real, epsabs: Real)
+        -> GSLResult<GSLNevalResult>
+    where R: FnMut() -> GSLResult<(Real, Real)>
+{
+    let mut nruns = 0;
+    let mut sum = 0.0;
+    let mut sum_var = 0.0;
+    loop {
+        let before = neval.get();
+        let (value, error) = run()?;
+        nruns += 1;
+        sum += value;
+        sum_var += error * error;
+
+        let value = sum / nruns as Real;
+        let error = sum_var.sqrt() / nruns as Real;
+        if meets_tolerance(value, error, epsrel, epsabs) {
+            return Ok(GSLNevalResult {
+                value, error,
+                neval: neval.get(),
+            });
+        }
+        if 2 * neval.get() - before > maxcalls {
+            return Err(GSLIntegrationError::GSLError(GSLErrorCode::MaxIter));
+        }
+    }
+}
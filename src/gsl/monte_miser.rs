@@ -0,0 +1,215 @@
+use std::cell::Cell;
+use std::mem;
+
+use ::bindings;
+use ::{Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{GSLIntegrationError, GSLNevalResult};
+use super::monte::{counted, integrate_runs, make_monte_function, GSLRng, MonteRange};
+
+struct MiserState {
+    state: *mut bindings::gsl_monte_miser_state
+}
+
+impl MiserState {
+    fn new(ndim: usize) -> Self {
+        let state = unsafe { bindings::gsl_monte_miser_alloc(ndim) };
+        assert!(!state.is_null(), "could not allocate GSL monte miser state");
+        MiserState { state }
+    }
+}
+
+impl Drop for MiserState {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_monte_miser_free(self.state)
+        }
+    }
+}
+
+/// The MISER algorithm of Press and Farrar, which is Monte Carlo
+/// integration with recursive stratified sampling. The box is bisected
+/// along the dimension which most reduces the variance, and more points are
+/// sampled where the integrand varies most, so it converges much faster
+/// than plain Monte Carlo for integrands with localized features.
+///
+/// GSL integrates with a fixed number of `calls`. To meet the requested
+/// tolerance, independent runs of `calls` evaluations are averaged until the
+/// error of the average is small enough, or another run would exceed
+/// `maxcalls` evaluations, in which case the integration fails with
+/// `GSLErrorCode::MaxIter`. The integrand must return a single value.
+///
+/// The random number generator is kept between integrations, so repeated
+/// integrations give independent results, unless it is reseeded with
+/// `with_seed`.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/montecarlo.html#miser).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real3};
+///
+/// let res = gsl::MonteMiser::new()
+///                           .with_calls(100000)
+///                           .integrate(|(x, y, z): Real3| {
+///                               if x * x + y * y + z * z < 1.0 { 1.0 } else { 0.0 }
+///                           }, 1e-2, 1e-12)
+///                           .unwrap();
+/// assert!((res.value - ::std::f64::consts::PI / 6.0).abs() < 5e-2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MonteMiser {
+    calls: usize,
+    maxcalls: usize,
+    range: Option<MonteRange>,
+    rng: GSLRng,
+    estimate_frac: Real,
+    min_calls: Option<usize>,
+    min_calls_per_bisection: Option<usize>,
+    alpha: Real,
+    dither: Real,
+}
+
+impl Default for MonteMiser {
+    fn default() -> Self {
+        MonteMiser {
+            calls: 10000,
+            maxcalls: 1000000,
+            range: None,
+            rng: GSLRng::new(0),
+            estimate_frac: 0.1,
+            min_calls: None,
+            min_calls_per_bisection: None,
+            alpha: 2.0,
+            dither: 0.0,
+        }
+    }
+}
+
+impl MonteMiser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of integrand evaluations of each run. (Default = 10000)
+    pub fn with_calls(self, calls: usize) -> Self {
+        MonteMiser { calls, ..self }
+    }
+
+    /// Set the maximum number of integrand evaluations, over all runs.
+    /// (Default = 1000000)
+    pub fn with_maxcalls(self, maxcalls: usize) -> Self {
+        MonteMiser { maxcalls, ..self }
+    }
+
+    /// Integrate over `range`, instead of the unit hypercube.
+    pub fn with_range(self, range: MonteRange) -> Self {
+        MonteMiser { range: Some(range), ..self }
+    }
+
+    /// Integrate over the unit hypercube. (The default)
+    pub fn without_range(self) -> Self {
+        MonteMiser { range: None, ..self }
+    }
+
+    /// Reseed the random number generator. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        MonteMiser { rng: GSLRng::new(seed), ..self }
+    }
+
+    /// Set the fraction of the calls in each region used to estimate the
+    /// variance before bisecting it. (Default = 0.1)
+    pub fn with_estimate_frac(self, estimate_frac: Real) -> Self {
+        MonteMiser { estimate_frac, ..self }
+    }
+
+    /// Set the minimum number of calls to estimate the variance of a
+    /// region with. (Default = 16 times the number of dimensions)
+    pub fn with_min_calls(self, min_calls: usize) -> Self {
+        MonteMiser { min_calls: Some(min_calls), ..self }
+    }
+
+    /// Set the number of calls below which a region is not bisected, and
+    /// integrated with plain Monte Carlo instead.
+    /// (Default = 32 times `min_calls`)
+    pub fn with_min_calls_per_bisection(self, min_calls_per_bisection: usize) -> Self {
+        MonteMiser { min_calls_per_bisection: Some(min_calls_per_bisection), ..self }
+    }
+
+    /// Set the exponent which decides how calls are distributed between
+    /// the halves of a bisected region. (Default = 2)
+    pub fn with_alpha(self, alpha: Real) -> Self {
+        MonteMiser { alpha, ..self }
+    }
+
+    /// Set the relative random offset of bisections from the middle of a
+    /// region, which can break symmetries of the integrand. (Default = 0)
+    pub fn with_dither(self, dither: Real) -> Self {
+        MonteMiser { dither, ..self }
+    }
+
+    pub fn range(&self) -> Option<&MonteRange> {
+        self.range.as_ref()
+    }
+}
+
+impl Integrator for MonteMiser {
+    type Success = GSLNevalResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if self.calls < 2 {
+            return Err(GSLIntegrationError::InvalidParameter(
+                "monte carlo integration needs at least 2 calls to estimate the error"));
+        }
+        let ndim = A::input_size();
+        let range = self.range.clone().unwrap_or_else(|| MonteRange::unit(ndim));
+
+        let neval = Cell::new(0);
+        let mut lp = LandingPad::new(counted(&neval, fun));
+        let res = {
+            let mut gslfn = make_monte_function(&mut lp, &range)?;
+            let state = MiserState::new(ndim);
+            unsafe {
+                let mut params: bindings::gsl_monte_miser_params = mem::zeroed();
+                bindings::gsl_monte_miser_params_get(state.state, &mut params);
+                params.estimate_frac = self.estimate_frac;
+                params.alpha = self.alpha;
+                params.dither = self.dither;
+                if let Some(min_calls) = self.min_calls {
+                    params.min_calls = min_calls;
+                    params.min_calls_per_bisection = 32 * min_calls;
+                }
+                if let Some(min_calls_per_bisection) = self.min_calls_per_bisection {
+                    params.min_calls_per_bisection = min_calls_per_bisection;
+                }
+                bindings::gsl_monte_miser_params_set(state.state, &params);
+            }
+            integrate_runs(|| {
+                let mut value: Real = 0.0;
+                let mut error: Real = 0.0;
+                let retcode = unsafe {
+                    bindings::gsl_monte_miser_integrate(&mut gslfn.function,
+                                                        range.xl().as_ptr(),
+                                                        range.xu().as_ptr(),
+                                                        ndim, self.calls,
+                                                        self.rng.rng,
+                                                        state.state,
+                                                        &mut value,
+                                                        &mut error)
+                };
+                if retcode != bindings::GSL_SUCCESS {
+                    Err(GSLIntegrationError::GSLError(retcode.into()))
+                } else {
+                    Ok((value, error))
+                }
+            }, &neval, self.maxcalls, epsrel, epsabs)
+        };
+        lp.maybe_resume_unwind();
+        res
+    }
+}
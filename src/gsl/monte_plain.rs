@@ -0,0 +1,154 @@
+use std::cell::Cell;
+
+use ::bindings;
+use ::{Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{GSLIntegrationError, GSLNevalResult};
+use super::monte::{counted, integrate_runs, make_monte_function, GSLRng, MonteRange};
+
+struct PlainState {
+    state: *mut bindings::gsl_monte_plain_state
+}
+
+impl PlainState {
+    fn new(ndim: usize) -> Self {
+        let state = unsafe { bindings::gsl_monte_plain_alloc(ndim) };
+        assert!(!state.is_null(), "could not allocate GSL monte plain state");
+        PlainState { state }
+    }
+}
+
+impl Drop for PlainState {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_monte_plain_free(self.state)
+        }
+    }
+}
+
+/// Plain Monte Carlo integration, which samples uniformly random points of
+/// a box in any number of dimensions. The error shrinks as `1/sqrt(n)`.
+///
+/// GSL integrates with a fixed number of `calls`. To meet the requested
+/// tolerance, independent runs of `calls` evaluations are averaged until the
+/// error of the average is small enough, or another run would exceed
+/// `maxcalls` evaluations, in which case the integration fails with
+/// `GSLErrorCode::MaxIter`. The integrand must return a single value.
+///
+/// The random number generator is kept between integrations, so repeated
+/// integrations give independent results, unless it is reseeded with
+/// `with_seed`.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/montecarlo.html#plain-monte-carlo).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real2};
+///
+/// let range = gsl::MonteRange::new((0.0, 0.0), (1.0, 2.0)).unwrap();
+/// let res = gsl::MontePlain::new()
+///                           .with_range(range)
+///                           .integrate(|(x, y): Real2| x * y, 1e-2, 1e-12)
+///                           .unwrap();
+/// assert!((res.value - 1.0).abs() < 5e-2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MontePlain {
+    calls: usize,
+    maxcalls: usize,
+    range: Option<MonteRange>,
+    rng: GSLRng,
+}
+
+impl Default for MontePlain {
+    fn default() -> Self {
+        MontePlain {
+            calls: 10000,
+            maxcalls: 1000000,
+            range: None,
+            rng: GSLRng::new(0),
+        }
+    }
+}
+
+impl MontePlain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of integrand evaluations of each run. (Default = 10000)
+    pub fn with_calls(self, calls: usize) -> Self {
+        MontePlain { calls, ..self }
+    }
+
+    /// Set the maximum number of integrand evaluations, over all runs.
+    /// (Default = 1000000)
+    pub fn with_maxcalls(self, maxcalls: usize) -> Self {
+        MontePlain { maxcalls, ..self }
+    }
+
+    /// Integrate over `range`, instead of the unit hypercube.
+    pub fn with_range(self, range: MonteRange) -> Self {
+        MontePlain { range: Some(range), ..self }
+    }
+
+    /// Integrate over the unit hypercube. (The default)
+    pub fn without_range(self) -> Self {
+        MontePlain { range: None, ..self }
+    }
+
+    /// Reseed the random number generator. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        MontePlain { rng: GSLRng::new(seed), ..self }
+    }
+
+    pub fn range(&self) -> Option<&MonteRange> {
+        self.range.as_ref()
+    }
+}
+
+impl Integrator for MontePlain {
+    type Success = GSLNevalResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if self.calls < 2 {
+            return Err(GSLIntegrationError::InvalidParameter(
+                "monte carlo integration needs at least 2 calls to estimate the error"));
+        }
+        let ndim = A::input_size();
+        let range = self.range.clone().unwrap_or_else(|| MonteRange::unit(ndim));
+
+        let neval = Cell::new(0);
+        let mut lp = LandingPad::new(counted(&neval, fun));
+        let res = {
+            let gslfn = make_monte_function(&mut lp, &range)?;
+            let state = PlainState::new(ndim);
+            integrate_runs(|| {
+                let mut value: Real = 0.0;
+                let mut error: Real = 0.0;
+                let retcode = unsafe {
+                    bindings::gsl_monte_plain_integrate(&gslfn.function,
+                                                        range.xl().as_ptr(),
+                                                        range.xu().as_ptr(),
+                                                        ndim, self.calls,
+                                                        self.rng.rng,
+                                                        state.state,
+                                                        &mut value,
+                                                        &mut error)
+                };
+                if retcode != bindings::GSL_SUCCESS {
+                    Err(GSLIntegrationError::GSLError(retcode.into()))
+                } else {
+                    Ok((value, error))
+                }
+            }, &neval, self.maxcalls, epsrel, epsabs)
+        };
+        lp.maybe_resume_unwind();
+        res
+    }
+}
@@ -0,0 +1,281 @@
+use std::cell::Cell;
+use std::mem;
+use std::os::raw::c_int;
+
+use ::bindings;
+use ::{IntegrationResult, IntegrationResultIter, IntegrationResults, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{GSLErrorCode, GSLIntegrationError};
+use super::monte::{counted, make_monte_function, meets_tolerance, GSLRng, MonteRange};
+
+struct VegasState {
+    state: *mut bindings::gsl_monte_vegas_state
+}
+
+impl VegasState {
+    fn new(ndim: usize) -> Self {
+        let state = unsafe { bindings::gsl_monte_vegas_alloc(ndim) };
+        assert!(!state.is_null(), "could not allocate GSL monte vegas state");
+        VegasState { state }
+    }
+}
+
+impl Drop for VegasState {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_monte_vegas_free(self.state)
+        }
+    }
+}
+
+/// How GSL's VEGAS samples points.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GSLVegasMode {
+    /// Importance sampling, together with stratified sampling when there
+    /// are enough calls for it.
+    Importance,
+    /// Importance sampling only.
+    ImportanceOnly,
+    /// Stratified sampling only, without importance sampling.
+    Stratified,
+}
+
+impl GSLVegasMode {
+    fn raw(&self) -> c_int {
+        match *self {
+            GSLVegasMode::Importance => bindings::GSL_VEGAS_MODE_IMPORTANCE as c_int,
+            GSLVegasMode::ImportanceOnly => bindings::GSL_VEGAS_MODE_IMPORTANCE_ONLY as c_int,
+            GSLVegasMode::Stratified => bindings::GSL_VEGAS_MODE_STRATIFIED as c_int,
+        }
+    }
+}
+
+/// The result of GSL's VEGAS, with the chi-squared of its iterations.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GSLVegasResult {
+    pub value: Real,
+    pub error: Real,
+    /// The number of integrand evaluations used, including the warm-up.
+    pub neval: usize,
+    /// The chi-squared per degree of freedom of the iterations' estimates,
+    /// which should be close to 1. If it is not, the estimates are
+    /// inconsistent, and the error is not reliable.
+    pub chi2_dof: Real,
+}
+
+impl IntegrationResults for GSLVegasResult {
+    type Iterator = IntegrationResultIter;
+    fn results(self) -> Self::Iterator {
+        IntegrationResult {
+            value: self.value,
+            error: self.error,
+        }.results()
+    }
+}
+
+/// The VEGAS algorithm of Lepage, which is Monte Carlo integration with
+/// adaptive importance sampling. The sampling density adapts to the
+/// integrand over a number of iterations, each of about `calls`
+/// evaluations.
+///
+/// First, `warmup` evaluations adapt the grid, and their results are
+/// discarded. Then, GSL's VEGAS is run with `calls` evaluations at a time,
+/// accumulating the results of its iterations, until their error meets the
+/// requested tolerance, or another run would exceed `maxcalls` evaluations,
+/// in which case the integration fails with `GSLErrorCode::MaxIter`. The
+/// integrand must return a single value.
+///
+/// The random number generator is kept between integrations, so repeated
+/// integrations give independent results, unless it is reseeded with
+/// `with_seed`.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/montecarlo.html#vegas).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real2};
+///
+/// let range = gsl::MonteRange::new((-5.0, -5.0), (5.0, 5.0)).unwrap();
+/// let res = gsl::MonteVegas::new()
+///                           .with_range(range)
+///                           .integrate(|(x, y): Real2| (-(x * x + y * y)).exp(),
+///                                      1e-3, 1e-12)
+///                           .unwrap();
+/// assert!((res.value - ::std::f64::consts::PI).abs() < 1e-2);
+/// assert!(res.chi2_dof < 5.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MonteVegas {
+    calls: usize,
+    warmup: usize,
+    maxcalls: usize,
+    iterations: usize,
+    alpha: Real,
+    mode: GSLVegasMode,
+    range: Option<MonteRange>,
+    rng: GSLRng,
+}
+
+impl Default for MonteVegas {
+    fn default() -> Self {
+        MonteVegas {
+            calls: 10000,
+            warmup: 10000,
+            maxcalls: 1000000,
+            iterations: 5,
+            alpha: 1.5,
+            mode: GSLVegasMode::Importance,
+            range: None,
+            rng: GSLRng::new(0),
+        }
+    }
+}
+
+impl MonteVegas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of integrand evaluations of each iteration.
+    /// (Default = 10000)
+    pub fn with_calls(self, calls: usize) -> Self {
+        MonteVegas { calls, ..self }
+    }
+
+    /// Set the number of integrand evaluations of each iteration of the
+    /// warm-up, whose results are discarded. 0 disables the warm-up.
+    /// (Default = 10000)
+    pub fn with_warmup(self, warmup: usize) -> Self {
+        MonteVegas { warmup, ..self }
+    }
+
+    /// Set the maximum number of integrand evaluations, including the
+    /// warm-up. (Default = 1000000)
+    pub fn with_maxcalls(self, maxcalls: usize) -> Self {
+        MonteVegas { maxcalls, ..self }
+    }
+
+    /// Set the number of iterations of each run. (Default = 5)
+    pub fn with_iterations(self, iterations: usize) -> Self {
+        MonteVegas { iterations, ..self }
+    }
+
+    /// Set the stiffness of the grid adaptation. 0 keeps the grid fixed,
+    /// and larger values adapt it faster. (Default = 1.5)
+    pub fn with_alpha(self, alpha: Real) -> Self {
+        MonteVegas { alpha, ..self }
+    }
+
+    /// Use a different sampling mode. (Default = `GSLVegasMode::Importance`)
+    pub fn with_mode(self, mode: GSLVegasMode) -> Self {
+        MonteVegas { mode, ..self }
+    }
+
+    /// Integrate over `range`, instead of the unit hypercube.
+    pub fn with_range(self, range: MonteRange) -> Self {
+        MonteVegas { range: Some(range), ..self }
+    }
+
+    /// Integrate over the unit hypercube. (The default)
+    pub fn without_range(self) -> Self {
+        MonteVegas { range: None, ..self }
+    }
+
+    /// Reseed the random number generator. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        MonteVegas { rng: GSLRng::new(seed), ..self }
+    }
+
+    pub fn range(&self) -> Option<&MonteRange> {
+        self.range.as_ref()
+    }
+
+    pub fn mode(&self) -> GSLVegasMode {
+        self.mode
+    }
+}
+
+impl Integrator for MonteVegas {
+    type Success = GSLVegasResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if self.calls < 2 || self.iterations == 0 {
+            return Err(GSLIntegrationError::InvalidParameter(
+                "vegas needs at least 2 calls and 1 iteration to estimate the error"));
+        }
+        let ndim = A::input_size();
+        let range = self.range.clone().unwrap_or_else(|| MonteRange::unit(ndim));
+        let mut xl = range.xl().to_vec();
+        let mut xu = range.xu().to_vec();
+
+        let neval = Cell::new(0);
+        let mut lp = LandingPad::new(counted(&neval, fun));
+        let res = {
+            let mut gslfn = make_monte_function(&mut lp, &range)?;
+            let state = VegasState::new(ndim);
+            let mut params: bindings::gsl_monte_vegas_params = unsafe { mem::zeroed() };
+            unsafe { bindings::gsl_monte_vegas_params_get(state.state, &mut params) };
+            params.alpha = self.alpha;
+            params.iterations = self.iterations;
+            params.mode = self.mode.raw();
+
+            let rng = self.rng.rng;
+            let mut vegas = |calls: usize, stage: c_int| {
+                let mut value: Real = 0.0;
+                let mut error: Real = 0.0;
+                params.stage = stage;
+                let retcode = unsafe {
+                    bindings::gsl_monte_vegas_params_set(state.state, &params);
+                    bindings::gsl_monte_vegas_integrate(&mut gslfn.function,
+                                                        xl.as_mut_ptr(),
+                                                        xu.as_mut_ptr(),
+                                                        ndim, calls, rng,
+                                                        state.state,
+                                                        &mut value,
+                                                        &mut error)
+                };
+                if retcode != bindings::GSL_SUCCESS {
+                    Err(GSLIntegrationError::GSLError(retcode.into()))
+                } else {
+                    Ok((value, error))
+                }
+            };
+
+            // Stage 0 starts with a new grid, stage 1 keeps the grid but
+            // discards the results so far, and stage 3 continues from the
+            // last run, accumulating its results. The runs are collected
+            // into a `Result`, so a panic in the integrand is resumed below
+            // even if GSL then fails.
+            let mut run = || {
+                let mut stage = 0;
+                if self.warmup > 0 {
+                    vegas(self.warmup, stage)?;
+                    stage = 1;
+                }
+                loop {
+                    let before = neval.get();
+                    let (value, error) = vegas(self.calls, stage)?;
+                    stage = 3;
+                    if meets_tolerance(value, error, epsrel, epsabs) {
+                        return Ok(GSLVegasResult {
+                            value, error,
+                            neval: neval.get(),
+                            chi2_dof: unsafe { bindings::gsl_monte_vegas_chisq(state.state) },
+                        });
+                    }
+                    if 2 * neval.get() - before > self.maxcalls {
+                        return Err(GSLIntegrationError::GSLError(GSLErrorCode::MaxIter));
+                    }
+                }
+            };
+            run()
+        };
+        lp.maybe_resume_unwind();
+        res
+    }
+}
@@ -0,0 +1,129 @@
+use std::convert::Into;
+
+use ::bindings;
+use ::{IntegrationResult, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLIntegrationWorkspace, GSLResult};
+
+/// Quadrature Adaptive integration with Weight for Cauchy principal values.
+/// Computes the principal value of the integral of `f(x) / (x - c)` over a
+/// finite range containing the pole `c`, using a Clenshaw-Curtis rule built
+/// for the weight on subintervals containing the pole, and Gauss-Kronrod
+/// integration elsewhere.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#qawc-adaptive-integration-for-cauchy-principal-values).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+///
+/// // PV \int_0^3 dx / (x - 1) = log(2)
+/// let mut qawc = gsl::QAWC::new(1000, 0.0, 3.0, 1.0).unwrap();
+///
+/// let res = qawc.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+///               .unwrap();
+/// assert!((res.value - 2f64.ln()).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QAWC {
+    range_low: Real,
+    range_high: Real,
+    pole: Real,
+    wkspc: GSLIntegrationWorkspace,
+}
+
+fn verify_pole(range_low: Real, range_high: Real, pole: Real) -> GSLResult<()> {
+    let (low, high) = if range_low <= range_high {
+        (range_low, range_high)
+    } else {
+        (range_high, range_low)
+    };
+    if (pole > low) & (pole < high) {
+        Ok(())
+    } else {
+        Err(GSLIntegrationError::InvalidParameter(
+            "QAWC pole must lie strictly inside the integration range"))
+    }
+}
+
+impl QAWC {
+    /// Creates a new QAWC with enough memory for `nintervals` subintervals,
+    /// which will integrate from `range_low` to `range_high` with a pole at
+    /// `pole`.
+    /// Returns `Err(GSLIntegrationError::InvalidParameter(..))` if the pole
+    /// does not lie strictly between the ends of the range.
+    pub fn new(nintervals: usize, range_low: Real, range_high: Real, pole: Real) -> GSLResult<Self> {
+        verify_pole(range_low, range_high, pole)?;
+        Ok(QAWC {
+            range_low, range_high, pole,
+            wkspc: GSLIntegrationWorkspace::new(nintervals)
+        })
+    }
+
+    /// Discards the old workspace and allocates a new one with enough memory
+    /// for `nintervals` subintervals.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        QAWC {
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            ..self
+        }
+    }
+
+    /// Use a different integration range. As with `QAWC::new()`, returns an
+    /// error if the pole does not lie strictly inside the new range.
+    pub fn with_range(self, range_low: Real, range_high: Real) -> GSLResult<Self> {
+        verify_pole(range_low, range_high, self.pole)?;
+        Ok(QAWC { range_low, range_high, ..self })
+    }
+
+    /// Use a different pole. As with `QAWC::new()`, returns an error if the
+    /// pole does not lie strictly inside the integration range.
+    pub fn with_pole(self, pole: Real) -> GSLResult<Self> {
+        verify_pole(self.range_low, self.range_high, pole)?;
+        Ok(QAWC { pole, ..self })
+    }
+
+    pub fn range(&self) -> (Real, Real) {
+        (self.range_low, self.range_high)
+    }
+
+    pub fn pole(&self) -> Real {
+        self.pole
+    }
+}
+
+impl Integrator for QAWC {
+    type Success = IntegrationResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let mut value: Real = 0.0;
+        let mut error: Real = 0.0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let mut gslfn = make_gsl_function(&mut lp, self.range_low, self.range_high)?;
+            bindings::gsl_integration_qawc(&mut gslfn.function,
+                                           self.range_low, self.range_high,
+                                           self.pole,
+                                           epsabs, epsrel,
+                                           self.wkspc.nintervals,
+                                           self.wkspc.wkspc,
+                                           &mut value,
+                                           &mut error)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(IntegrationResult {
+                value, error
+            })
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::convert::Into;
+
+use ::bindings;
+use ::{IntegrationResult, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLIntegrationWorkspace};
+use super::qawo::{QAWOTable, QAWOWeight};
+
+/// Quadrature Adaptive integration with Weight for Fourier integrals.
+/// Integrates `f(x) sin(omega x)` or `f(x) cos(omega x)` over the
+/// semi-infinite range `(a, +inf)`, by applying `QAWO` to each period of the
+/// weight in turn, and extrapolating the resulting series. This works even
+/// when `f(x)` decays too slowly for `QAGIU` to handle the oscillation.
+///
+/// Note that QAWF only accepts an absolute tolerance, so the `epsrel`
+/// argument to `integrate` is ignored.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#qawf-adaptive-integration-for-fourier-integrals).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+///
+/// let mut qawf = gsl::QAWF::new(1000, 50, 0.0)
+///                          .with_weight(gsl::QAWOWeight::Cosine);
+///
+/// // Integrates exp(-x) cos(x) from 0 to infinity
+/// let res = qawf.integrate(|x: Real| (-x).exp(), 0.0, 1e-10)
+///               .unwrap();
+/// assert!((res.value - 0.5).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QAWF {
+    lower_bound: Real,
+    wkspc: GSLIntegrationWorkspace,
+    cycle_wkspc: GSLIntegrationWorkspace,
+    table: QAWOTable,
+}
+
+impl QAWF {
+    /// Creates a new QAWF with enough memory for `nintervals` subintervals
+    /// in each of its workspaces, and a table of Chebyshev moments for
+    /// `nlevels` levels of bisection. This will integrate `f(x) sin(x)` from
+    /// `lower_bound` to +infinity. To change the weight, see `with_omega` and
+    /// `with_weight`.
+    ///
+    /// # Panics
+    /// If `nlevels` is 0.
+    pub fn new(nintervals: usize, nlevels: usize, lower_bound: Real) -> Self {
+        QAWF {
+            lower_bound,
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            cycle_wkspc: GSLIntegrationWorkspace::new(nintervals),
+            // The length of the table is overridden by GSL for each period.
+            table: QAWOTable::new(1.0, 1.0, QAWOWeight::Sine, nlevels),
+        }
+    }
+
+    /// Discards the old workspaces and allocates new ones with enough memory
+    /// for `nintervals` subintervals.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        QAWF {
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            cycle_wkspc: GSLIntegrationWorkspace::new(nintervals),
+            ..self
+        }
+    }
+
+    /// Discards the old table and allocates a new one for `nlevels` levels of
+    /// bisection.
+    ///
+    /// # Panics
+    /// If `nlevels` is 0.
+    pub fn with_nlevels(self, nlevels: usize) -> Self {
+        let table = QAWOTable::new(self.table.omega(), self.table.length(),
+                                   self.table.weight(), nlevels);
+        QAWF { table, ..self }
+    }
+
+    /// Use a different angular frequency `omega`. (Default = 1)
+    pub fn with_omega(mut self, omega: Real) -> Self {
+        let (length, weight) = (self.table.length(), self.table.weight());
+        self.table.set(omega, length, weight);
+        self
+    }
+
+    /// Use a different weight function. (Default = `QAWOWeight::Sine`)
+    pub fn with_weight(mut self, weight: QAWOWeight) -> Self {
+        let (omega, length) = (self.table.omega(), self.table.length());
+        self.table.set(omega, length, weight);
+        self
+    }
+
+    /// Integrate from a different lower bound.
+    pub fn with_lower_bound(self, lower_bound: Real) -> Self {
+        QAWF { lower_bound, ..self }
+    }
+
+    pub fn omega(&self) -> Real {
+        self.table.omega()
+    }
+
+    pub fn weight(&self) -> QAWOWeight {
+        self.table.weight()
+    }
+
+    pub fn lower_bound(&self) -> Real {
+        self.lower_bound
+    }
+}
+
+impl Integrator for QAWF {
+    type Success = IntegrationResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, _epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let mut value: Real = 0.0;
+        let mut error: Real = 0.0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let mut gslfn = make_gsl_function(&mut lp,
+                                              self.lower_bound,
+                                              self.lower_bound + 1.0)?;
+            bindings::gsl_integration_qawf(&mut gslfn.function,
+                                           self.lower_bound,
+                                           epsabs,
+                                           self.wkspc.nintervals,
+                                           self.wkspc.wkspc,
+                                           self.cycle_wkspc.wkspc,
+                                           self.table.table,
+                                           &mut value,
+                                           &mut error)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(IntegrationResult {
+                value, error
+            })
+        }
+    }
+}
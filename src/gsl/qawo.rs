@@ -0,0 +1,248 @@
+use std::convert::Into;
+use std::fmt;
+
+use ::bindings;
+use ::{IntegrationResult, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLIntegrationWorkspace};
+
+/// The oscillatory weight applied to the integrand by `QAWO` and `QAWF`.
+#[derive(Debug, Hash, Copy, Clone, PartialEq, Eq)]
+pub enum QAWOWeight {
+    /// Integrate `f(x) sin(omega x)`.
+    Sine,
+    /// Integrate `f(x) cos(omega x)`.
+    Cosine,
+}
+
+impl Into<bindings::gsl_integration_qawo_enum> for QAWOWeight {
+    fn into(self) -> bindings::gsl_integration_qawo_enum {
+        match self {
+            QAWOWeight::Sine => bindings::gsl_integration_qawo_enum_GSL_INTEG_SINE,
+            QAWOWeight::Cosine => bindings::gsl_integration_qawo_enum_GSL_INTEG_COSINE,
+        }
+    }
+}
+
+/// A table of Chebyshev moments of the oscillatory weight, for a given
+/// `omega`, interval length, and number of bisection levels.
+pub(super) struct QAWOTable {
+    omega: Real,
+    length: Real,
+    weight: QAWOWeight,
+    pub(super) nlevels: usize,
+    pub(super) table: *mut bindings::gsl_integration_qawo_table
+}
+
+impl fmt::Debug for QAWOTable {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("QAWOTable")
+           .field("omega", &self.omega)
+           .field("length", &self.length)
+           .field("weight", &self.weight)
+           .field("nlevels", &self.nlevels)
+           .finish()
+    }
+}
+
+impl Clone for QAWOTable {
+    fn clone(&self) -> Self {
+        QAWOTable::new(self.omega, self.length, self.weight, self.nlevels)
+    }
+}
+
+impl QAWOTable {
+    /// # Panics
+    /// If `nlevels` is 0.
+    pub(super) fn new(omega: Real, length: Real, weight: QAWOWeight, nlevels: usize) -> Self {
+        assert!(nlevels > 0, "QAWO table must have at least one level");
+        unsafe { bindings::gsl_set_error_handler_off() };
+        QAWOTable {
+            table: unsafe {
+                bindings::gsl_integration_qawo_table_alloc(omega, length,
+                                                           weight.into(),
+                                                           nlevels)
+            },
+            omega, length, weight, nlevels
+        }
+    }
+
+    /// Recomputes the moments, if any parameter changed.
+    pub(super) fn set(&mut self, omega: Real, length: Real, weight: QAWOWeight) {
+        if (omega, weight) != (self.omega, self.weight) {
+            unsafe {
+                bindings::gsl_integration_qawo_table_set(self.table, omega,
+                                                         length,
+                                                         weight.into())
+            };
+        } else if length != self.length {
+            unsafe {
+                bindings::gsl_integration_qawo_table_set_length(self.table,
+                                                                length)
+            };
+        }
+        self.omega = omega;
+        self.length = length;
+        self.weight = weight;
+    }
+
+    pub(super) fn omega(&self) -> Real {
+        self.omega
+    }
+
+    pub(super) fn length(&self) -> Real {
+        self.length
+    }
+
+    pub(super) fn weight(&self) -> QAWOWeight {
+        self.weight
+    }
+}
+
+impl Drop for QAWOTable {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_integration_qawo_table_free(self.table)
+        }
+    }
+}
+
+/// Quadrature Adaptive integration with Weight for Oscillatory functions.
+/// Integrates `f(x) sin(omega x)` or `f(x) cos(omega x)` over a finite
+/// range, using Clenshaw-Curtis integration on subintervals where the weight
+/// oscillates many times, and Gauss-Kronrod integration elsewhere.
+///
+/// The Chebyshev moments needed for the oscillatory subintervals are
+/// precomputed in a table, which is kept between integrations, and only
+/// recomputed when `omega`, the weight, or the length of the range change.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#qawo-adaptive-integration-for-oscillatory-functions).
+///
+/// ```
+/// use std::f64::consts::PI;
+/// use integrators::{gsl, Integrator, Real};
+///
+/// let mut qawo = gsl::QAWO::new(1000, 50)
+///                          .with_range(0.0, PI);
+///
+/// // Integrates x sin(x) from 0 to pi
+/// let res = qawo.integrate(|x: Real| x, 1e-8, 1e-12)
+///               .unwrap();
+/// assert!((res.value - PI).abs() < 1e-8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QAWO {
+    range_low: Real,
+    wkspc: GSLIntegrationWorkspace,
+    table: QAWOTable,
+}
+
+impl QAWO {
+    /// Creates a new QAWO with enough memory for `nintervals` subintervals,
+    /// and a table of Chebyshev moments for `nlevels` levels of bisection.
+    /// This will integrate `f(x) sin(x)` over the range [0, 1]. To change the
+    /// weight, see `with_omega` and `with_weight`, and to change the
+    /// integration bounds, see `with_range`.
+    ///
+    /// # Panics
+    /// If `nlevels` is 0.
+    pub fn new(nintervals: usize, nlevels: usize) -> Self {
+        QAWO {
+            range_low: 0.0,
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            table: QAWOTable::new(1.0, 1.0, QAWOWeight::Sine, nlevels),
+        }
+    }
+
+    /// Discards the old workspace and allocates a new one with enough memory
+    /// for `nintervals` subintervals.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        QAWO {
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            ..self
+        }
+    }
+
+    /// Discards the old table and allocates a new one for `nlevels` levels of
+    /// bisection. Integration cannot converge if it needs to subdivide the
+    /// range further than that.
+    ///
+    /// # Panics
+    /// If `nlevels` is 0.
+    pub fn with_nlevels(self, nlevels: usize) -> Self {
+        let table = QAWOTable::new(self.table.omega(), self.table.length(),
+                                   self.table.weight(), nlevels);
+        QAWO { table, ..self }
+    }
+
+    /// Use a different angular frequency `omega`. (Default = 1)
+    pub fn with_omega(mut self, omega: Real) -> Self {
+        let (length, weight) = (self.table.length(), self.table.weight());
+        self.table.set(omega, length, weight);
+        self
+    }
+
+    /// Use a different weight function. (Default = `QAWOWeight::Sine`)
+    pub fn with_weight(mut self, weight: QAWOWeight) -> Self {
+        let (omega, length) = (self.table.omega(), self.table.length());
+        self.table.set(omega, length, weight);
+        self
+    }
+
+    /// Use a different integration range. (Default = [0, 1])
+    pub fn with_range(mut self, range_low: Real, range_high: Real) -> Self {
+        let (omega, weight) = (self.table.omega(), self.table.weight());
+        self.table.set(omega, range_high - range_low, weight);
+        QAWO { range_low, ..self }
+    }
+
+    pub fn omega(&self) -> Real {
+        self.table.omega()
+    }
+
+    pub fn weight(&self) -> QAWOWeight {
+        self.table.weight()
+    }
+
+    pub fn range(&self) -> (Real, Real) {
+        (self.range_low, self.range_low + self.table.length())
+    }
+}
+
+impl Integrator for QAWO {
+    type Success = IntegrationResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let (range_low, range_high) = self.range();
+        let mut value: Real = 0.0;
+        let mut error: Real = 0.0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let mut gslfn = make_gsl_function(&mut lp, range_low, range_high)?;
+            bindings::gsl_integration_qawo(&mut gslfn.function,
+                                           range_low,
+                                           epsabs, epsrel,
+                                           self.wkspc.nintervals,
+                                           self.wkspc.wkspc,
+                                           self.table.table,
+                                           &mut value,
+                                           &mut error)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(IntegrationResult {
+                value, error
+            })
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::convert::Into;
+use std::fmt;
+use std::os::raw::c_int;
+
+use ::bindings;
+use ::{IntegrationResult, Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLIntegrationWorkspace, GSLResult};
+
+/// The algebraic-logarithmic weight function applied by `QAWS`,
+///
+/// `W(x) = (x - a)^alpha (b - x)^beta log^mu(x - a) log^nu(b - x)`,
+///
+/// where `[a, b]` is the integration range.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct QAWSWeight {
+    alpha: Real,
+    beta: Real,
+    mu: c_int,
+    nu: c_int,
+}
+
+impl QAWSWeight {
+    /// Creates a new weight function. `alpha` and `beta` must be greater than
+    /// -1, so that the weight is integrable, and `mu` and `nu` must each be 0
+    /// or 1. Otherwise, returns `Err(GSLIntegrationError::InvalidParameter)`.
+    pub fn new(alpha: Real, beta: Real, mu: c_int, nu: c_int) -> GSLResult<Self> {
+        if !(alpha > -1.0) {
+            Err(GSLIntegrationError::InvalidParameter("QAWS alpha must be greater than -1"))
+        } else if !(beta > -1.0) {
+            Err(GSLIntegrationError::InvalidParameter("QAWS beta must be greater than -1"))
+        } else if (mu != 0) & (mu != 1) {
+            Err(GSLIntegrationError::InvalidParameter("QAWS mu must be 0 or 1"))
+        } else if (nu != 0) & (nu != 1) {
+            Err(GSLIntegrationError::InvalidParameter("QAWS nu must be 0 or 1"))
+        } else {
+            Ok(QAWSWeight { alpha, beta, mu, nu })
+        }
+    }
+
+    pub fn alpha(&self) -> Real {
+        self.alpha
+    }
+
+    pub fn beta(&self) -> Real {
+        self.beta
+    }
+
+    pub fn mu(&self) -> c_int {
+        self.mu
+    }
+
+    pub fn nu(&self) -> c_int {
+        self.nu
+    }
+}
+
+/// GSL's precomputed Chebyshev moments of a `QAWSWeight`.
+struct QAWSTable {
+    weight: QAWSWeight,
+    table: *mut bindings::gsl_integration_qaws_table
+}
+
+impl fmt::Debug for QAWSTable {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("QAWSTable")
+           .field("weight", &self.weight)
+           .finish()
+    }
+}
+
+impl Clone for QAWSTable {
+    fn clone(&self) -> Self {
+        QAWSTable::new(self.weight)
+    }
+}
+
+impl QAWSTable {
+    fn new(weight: QAWSWeight) -> Self {
+        QAWSTable {
+            // The parameters have been checked by `QAWSWeight::new()`.
+            table: unsafe {
+                bindings::gsl_integration_qaws_table_alloc(weight.alpha,
+                                                           weight.beta,
+                                                           weight.mu,
+                                                           weight.nu)
+            },
+            weight
+        }
+    }
+
+    fn set(&mut self, weight: QAWSWeight) {
+        if weight != self.weight {
+            unsafe {
+                bindings::gsl_integration_qaws_table_set(self.table,
+                                                         weight.alpha,
+                                                         weight.beta,
+                                                         weight.mu,
+                                                         weight.nu)
+            };
+            self.weight = weight;
+        }
+    }
+}
+
+impl Drop for QAWSTable {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_integration_qaws_table_free(self.table)
+        }
+    }
+}
+
+/// Quadrature Adaptive integration with Weight for Singular functions.
+/// Integrates `f(x) W(x)`, where `W(x)` is an algebraic-logarithmic weight
+/// with (integrable) singularities at the endpoints of the range; see
+/// `QAWSWeight`. Subintervals touching an endpoint are integrated with a
+/// modified Clenshaw-Curtis rule built for the weight, so this needs far
+/// fewer evaluations than `QAGS` for such integrands.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#qaws-adaptive-integration-for-singular-functions).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+///
+/// // W(x) = x^(-1/2) log(x)
+/// let weight = gsl::QAWSWeight::new(-0.5, 0.0, 1, 0).unwrap();
+/// let mut qaws = gsl::QAWS::new(1000, weight);
+///
+/// let res = qaws.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+///               .unwrap();
+/// assert!((res.value + 4.0).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QAWS {
+    range_low: Real,
+    range_high: Real,
+    wkspc: GSLIntegrationWorkspace,
+    table: QAWSTable,
+}
+
+impl QAWS {
+    /// Creates a new QAWS with enough memory for `nintervals` subintervals,
+    /// which will integrate over [0, 1] with the given weight function. To
+    /// change the integration bounds, see `with_range`.
+    pub fn new(nintervals: usize, weight: QAWSWeight) -> Self {
+        QAWS {
+            range_low: 0.0,
+            range_high: 1.0,
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            table: QAWSTable::new(weight),
+        }
+    }
+
+    /// Discards the old workspace and allocates a new one with enough memory
+    /// for `nintervals` subintervals.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        QAWS {
+            wkspc: GSLIntegrationWorkspace::new(nintervals),
+            ..self
+        }
+    }
+
+    /// Use a different weight function, recomputing the table of moments.
+    pub fn with_weight(mut self, weight: QAWSWeight) -> Self {
+        self.table.set(weight);
+        self
+    }
+
+    /// Use a different integration range. (Default = [0, 1])
+    pub fn with_range(self, range_low: Real, range_high: Real) -> Self {
+        QAWS { range_low, range_high, ..self }
+    }
+
+    pub fn weight(&self) -> QAWSWeight {
+        self.table.weight
+    }
+}
+
+impl Integrator for QAWS {
+    type Success = IntegrationResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let mut value: Real = 0.0;
+        let mut error: Real = 0.0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let mut gslfn = make_gsl_function(&mut lp, self.range_low, self.range_high)?;
+            bindings::gsl_integration_qaws(&mut gslfn.function,
+                                           self.range_low, self.range_high,
+                                           self.table.table,
+                                           epsabs, epsrel,
+                                           self.wkspc.nintervals,
+                                           self.wkspc.wkspc,
+                                           &mut value,
+                                           &mut error)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(IntegrationResult {
+                value, error
+            })
+        }
+    }
+}
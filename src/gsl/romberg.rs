@@ -0,0 +1,151 @@
+use std::convert::Into;
+use std::fmt;
+
+use ::bindings;
+use ::{Integrator, Real};
+use ::ffi::LandingPad;
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{make_gsl_function, GSLIntegrationError, GSLNevalResult};
+
+/// GSL's Romberg integration supports at most this many iterations.
+pub const ROMBERG_MAX_ITER: usize = 30;
+
+struct RombergWorkspace {
+    niter: usize,
+    wkspc: *mut bindings::gsl_integration_romberg_workspace
+}
+
+impl fmt::Debug for RombergWorkspace {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("RombergWorkspace")
+           .field("niter", &self.niter)
+           .finish()
+    }
+}
+
+impl Clone for RombergWorkspace {
+    fn clone(&self) -> Self {
+        RombergWorkspace::new(self.niter)
+    }
+}
+
+impl RombergWorkspace {
+    fn new(n: usize) -> Self {
+        assert!((n >= 1) & (n <= ROMBERG_MAX_ITER),
+                "Romberg workspace needs between 1 and {} iterations",
+                ROMBERG_MAX_ITER);
+        RombergWorkspace {
+            wkspc: unsafe {
+                bindings::gsl_integration_romberg_alloc(n)
+            },
+            niter: n
+        }
+    }
+}
+
+impl Drop for RombergWorkspace {
+    fn drop(&mut self) {
+        unsafe {
+            bindings::gsl_integration_romberg_free(self.wkspc)
+        }
+    }
+}
+
+/// Romberg integration. Applies Richardson extrapolation to the trapezoid
+/// rule, doubling the number of points each iteration, until two successive
+/// estimates agree to the requested precision. For smooth integrands this
+/// converges very quickly, in few evaluations; it is poorly suited to
+/// integrands with singularities or discontinuities.
+///
+/// GSL does not provide an error estimate for Romberg integration, so the
+/// reported `error` is always NaN. On success, the last two estimates agreed
+/// to within `max(epsabs, epsrel * |value|)`.
+///
+/// See GSL docs
+/// [here](https://www.gnu.org/software/gsl/doc/html/integration.html#romberg-integration).
+///
+/// ```
+/// use integrators::{gsl, Integrator, Real};
+///
+/// let res = gsl::Romberg::new(20)
+///                        .with_range(0.0, 2.0)
+///                        .integrate(|x: Real| x.exp(), 1e-10, 1e-12)
+///                        .unwrap();
+/// assert!((res.value - (2f64.exp() - 1.0)).abs() < 1e-9);
+/// assert!(res.neval < 100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Romberg {
+    range_low: Real,
+    range_high: Real,
+    wkspc: RombergWorkspace,
+}
+
+impl Romberg {
+    /// Creates a new Romberg integrator, which will perform up to `niter`
+    /// iterations, each of which doubles the number of integrand evaluations.
+    /// This will integrate over [0, 1]. To change the integration bounds,
+    /// see `with_range`.
+    ///
+    /// # Panics
+    /// If `niter` is not between 1 and `ROMBERG_MAX_ITER`.
+    pub fn new(niter: usize) -> Self {
+        Romberg {
+            range_low: 0.0,
+            range_high: 1.0,
+            wkspc: RombergWorkspace::new(niter)
+        }
+    }
+
+    /// Discards the old workspace and allocates a new one for up to `niter`
+    /// iterations.
+    ///
+    /// # Panics
+    /// If `niter` is not between 1 and `ROMBERG_MAX_ITER`.
+    pub fn with_niter(self, niter: usize) -> Self {
+        Romberg {
+            wkspc: RombergWorkspace::new(niter),
+            ..self
+        }
+    }
+
+    /// Use a different integration range. (Default = [0, 1])
+    pub fn with_range(self, range_low: Real, range_high: Real) -> Self {
+        Romberg { range_low, range_high, ..self }
+    }
+}
+
+impl Integrator for Romberg {
+    type Success = GSLNevalResult;
+    type Failure = GSLIntegrationError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let mut value: Real = 0.0;
+        let mut neval: usize = 0;
+
+        let mut lp = LandingPad::new(fun);
+        let retcode = unsafe {
+            let gslfn = make_gsl_function(&mut lp, self.range_low, self.range_high)?;
+            bindings::gsl_integration_romberg(&gslfn.function,
+                                              self.range_low, self.range_high,
+                                              epsabs, epsrel,
+                                              &mut value,
+                                              &mut neval,
+                                              self.wkspc.wkspc)
+        };
+        lp.maybe_resume_unwind();
+
+        if retcode != bindings::GSL_SUCCESS {
+            Err(GSLIntegrationError::GSLError(retcode.into()))
+        } else {
+            Ok(GSLNevalResult {
+                value,
+                error: ::std::f64::NAN,
+                neval
+            })
+        }
+    }
+}
@@ -1,7 +1,10 @@
 //use std::intrinsics::unchecked_div;
 use ::Real;
-use ::Integrator;
-use super::{GSLIntegrationError, QNG, QAG, QAGS, QAGP};
+use ::{DynIntegrator, Integrator};
+use super::{GSLIntegrationError, QNG, QAG, QAGS, QAGP, QAWO, QAWOWeight, QAWF,
+            QAWS, QAWSWeight, QAWC, CQUAD, Romberg, GSLErrorCode,
+            FixedQuadrature, FixedRuleType, MonteRange, MontePlain, MonteMiser,
+            MonteVegas, GSLVegasMode, GLFixedND};
 
 fn nan(_: Real) -> Real {
     ::std::f64::NAN
@@ -124,3 +127,300 @@ fn test_qagp_singularity() {
                    .expect("integration should succeed");
     assert!((res2.value - 2f64).abs() <= res2.error);
 }
+
+#[test]
+fn test_qawo_oscillatory() {
+    let omega = 50.0;
+    let mut qawo = QAWO::new(1000, 50)
+                       .with_omega(omega)
+                       .with_weight(QAWOWeight::Cosine);
+    assert_eq!(qawo.range(), (0.0, 1.0));
+
+    // \int_0^1 x cos(omega x) dx
+    let exp = (omega.cos() + omega * omega.sin() - 1.0) / omega.powi(2);
+    let res = qawo.integrate(|x: Real| x, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - exp).abs() <= res.error.max(1e-10));
+
+    // Same length, so the table is reused: \int_1^2 x cos(omega x) dx
+    qawo = qawo.with_range(1.0, 2.0);
+    let antideriv = |x: Real| (omega * x).cos() / omega.powi(2)
+                              + x * (omega * x).sin() / omega;
+    let res = qawo.integrate(|x: Real| x, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - (antideriv(2.0) - antideriv(1.0))).abs() <= res.error.max(1e-10));
+
+    // \int_0^pi sin(x) dx
+    qawo = qawo.with_omega(1.0)
+               .with_weight(QAWOWeight::Sine)
+               .with_range(0.0, ::std::f64::consts::PI);
+    let res = qawo.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - 2.0).abs() <= res.error.max(1e-10));
+}
+
+#[test]
+fn test_qawf_fourier() {
+    use std::f64::consts::PI;
+
+    // \int_0^\infty cos(omega x) / (1 + x^2) dx = pi/2 exp(-omega)
+    let mut qawf = QAWF::new(1000, 50, 0.0)
+                       .with_weight(QAWOWeight::Cosine)
+                       .with_omega(2.0);
+    let res = qawf.integrate(|x: Real| 1.0 / (1.0 + x * x), 0.0, 1e-10)
+                  .expect("should converge");
+    assert!((res.value - PI / 2.0 * (-2f64).exp()).abs() <= res.error.max(1e-9));
+
+    // \int_1^\infty sin(x) / x dx = pi/2 - Si(1), which decays too slowly
+    // for QAGIU
+    let mut qawf = qawf.with_weight(QAWOWeight::Sine)
+                       .with_omega(1.0)
+                       .with_lower_bound(1.0);
+    let res = qawf.integrate(|x: Real| x.recip(), 0.0, 1e-10)
+                  .expect("should converge");
+    assert!((res.value - (PI / 2.0 - 0.946083070367183)).abs() <= res.error.max(1e-9));
+}
+
+#[test]
+fn test_qaws_weights() {
+    use std::f64::consts::PI;
+
+    assert_eq!(QAWSWeight::new(-1.0, 0.0, 0, 0),
+               Err(GSLIntegrationError::InvalidParameter("QAWS alpha must be greater than -1")));
+    assert_eq!(QAWSWeight::new(0.0, -1.5, 0, 0),
+               Err(GSLIntegrationError::InvalidParameter("QAWS beta must be greater than -1")));
+    assert_eq!(QAWSWeight::new(0.0, 0.0, 2, 0),
+               Err(GSLIntegrationError::InvalidParameter("QAWS mu must be 0 or 1")));
+    assert_eq!(QAWSWeight::new(0.0, 0.0, 0, -1),
+               Err(GSLIntegrationError::InvalidParameter("QAWS nu must be 0 or 1")));
+    assert!(QAWSWeight::new(::std::f64::NAN, 0.0, 0, 0).is_err());
+
+    // \int_0^1 x^(-1/2) (1-x)^(-1/2) dx = B(1/2, 1/2) = pi
+    let weight = QAWSWeight::new(-0.5, -0.5, 0, 0).expect("valid weight");
+    let mut qaws = QAWS::new(1000, weight);
+    let res = qaws.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - PI).abs() <= res.error.max(1e-10));
+
+    // \int_2^3 (x-2)^(-1/2) log(x-2) dx = -4
+    let weight = QAWSWeight::new(-0.5, 0.0, 1, 0).expect("valid weight");
+    qaws = qaws.with_weight(weight).with_range(2.0, 3.0);
+    assert_eq!(qaws.weight(), weight);
+    let res = qaws.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value + 4.0).abs() <= res.error.max(1e-10));
+}
+
+#[test]
+fn test_qawc_principal_value() {
+    let bad_pole = Err(GSLIntegrationError::InvalidParameter(
+        "QAWC pole must lie strictly inside the integration range"));
+    assert_eq!(QAWC::new(1000, 0.0, 1.0, 1.0).map(|q| q.pole()), bad_pole);
+    assert_eq!(QAWC::new(1000, 0.0, 1.0, -0.5).map(|q| q.pole()), bad_pole);
+
+    // PV \int_0^3 x / (x - 1) dx = 3 + log(2)
+    let mut qawc = QAWC::new(1000, 0.0, 3.0, 1.0).expect("pole inside range");
+    let res = qawc.integrate(|x: Real| x, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - (3.0 + 2f64.ln())).abs() <= res.error.max(1e-10));
+
+    assert_eq!(qawc.clone().with_pole(4.0).map(|q| q.pole()), bad_pole);
+    assert_eq!(qawc.clone().with_range(1.5, 3.0).map(|q| q.pole()), bad_pole);
+
+    // PV \int_0^1 dx / (x - 1/2) = 0
+    let mut qawc = qawc.with_range(0.0, 1.0)
+                       .and_then(|q| q.with_pole(0.5))
+                       .expect("pole inside range");
+    let res = qawc.integrate(|_: Real| 1.0, 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!(res.value.abs() <= res.error.max(1e-10));
+}
+
+#[test]
+fn test_cquad_bad_points() {
+    let mut cquad = CQUAD::new(100);
+
+    // NaN at a node of the rule
+    let res = cquad.integrate(|x: Real| if x == 0.5 { ::std::f64::NAN } else { x },
+                              1e-10, 1e-12)
+                   .expect("should converge");
+    assert!((res.value - 0.5).abs() <= res.error.max(1e-10));
+
+    // Infinite at an endpoint
+    let res = cquad.integrate(|x: Real| 1.0 / (1.0 - x).sqrt(), 1e-8, 1e-10)
+                   .expect("should converge");
+    assert!((res.value - 2.0).abs() <= res.error.max(1e-8));
+    assert!(res.neval > 0);
+
+    // Discontinuous
+    cquad = cquad.with_range(-1.0, 1.0);
+    let res = cquad.integrate(|x: Real| if x < 0.3 { 0.0 } else { 1.0 }, 1e-8, 1e-10)
+                   .expect("should converge");
+    assert!((res.value - 0.7).abs() <= res.error.max(1e-8));
+}
+
+#[test]
+fn test_romberg() {
+    let mut romberg = Romberg::new(20);
+    for &(low, high) in [(0.0, 1.0), (-3.0, 2.0), (10.0, 11.5)].iter() {
+        romberg = romberg.with_range(low, high);
+        let exp = quadratic_1_integral(low, high);
+        let res = romberg.integrate(quadratic_1, 1e-10, 1e-12)
+                         .expect("should converge");
+        assert!((res.value - exp).abs() < 1e-9);
+        assert!(res.error.is_nan());
+        assert!(res.neval <= (1 << 20) + 1);
+    }
+
+    // Too few iterations to converge
+    let mut romberg = Romberg::new(3);
+    let res = romberg.integrate(|x: Real| (10.0 * x).sin(), 1e-12, 1e-14);
+    assert_eq!(res, Err(GSLIntegrationError::GSLError(GSLErrorCode::MaxIter)));
+}
+
+#[test]
+fn test_fixed_quadrature() {
+    use std::f64::consts::PI;
+
+    // Exact for polynomials of degree up to 2n - 1
+    let mut legendre = FixedQuadrature::new(
+        FixedRuleType::Legendre { a: 0.0, b: 2.0 }, 3).unwrap();
+    assert_eq!(legendre.nodes().len(), 3);
+    assert!((legendre.weights().iter().sum::<Real>() - 2.0).abs() < 1e-12);
+    let res = legendre.integrate(|x: Real| x.powi(5), 0.0, 0.0).unwrap();
+    assert!((res.value - 64.0 / 6.0).abs() < 1e-10);
+
+    let mut chebyshev = legendre.with_rule(
+        FixedRuleType::Chebyshev { a: -1.0, b: 1.0 }).unwrap();
+    let res = chebyshev.integrate(|_: Real| 1.0, 0.0, 0.0).unwrap();
+    assert!((res.value - PI).abs() < 1e-10);
+
+    let mut hermite = chebyshev.with_rule(
+        FixedRuleType::Hermite { a: 0.0, b: 1.0, alpha: 0.0 }).unwrap()
+        .with_n(5).unwrap();
+    assert_eq!(hermite.n(), 5);
+    let res = hermite.integrate(|x: Real| x * x, 0.0, 0.0).unwrap();
+    assert!((res.value - PI.sqrt() / 2.0).abs() < 1e-10);
+
+    match FixedQuadrature::new(FixedRuleType::Laguerre { a: 0.0, b: 0.0, alpha: 0.0 }, 4) {
+        Err(GSLIntegrationError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+    match FixedQuadrature::new(FixedRuleType::Rational { a: 0.0, b: 1.0, alpha: 0.0, beta: 0.0 }, 4) {
+        Err(GSLIntegrationError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_monte_carlo() {
+    // The integral of x * y^2 over [0, 1] x [-1, 2]
+    let fun = |(x, y): (Real, Real)| {
+        assert!((x >= 0.0) & (x <= 1.0) & (y >= -1.0) & (y <= 2.0));
+        x * y * y
+    };
+    let range = MonteRange::new((0.0, -1.0), (1.0, 2.0)).unwrap();
+    let exp = 1.5;
+
+    let res = MontePlain::new().with_range(range.clone())
+                               .integrate(fun, 1e-2, 1e-12)
+                               .expect("should converge");
+    assert!((res.value - exp).abs() < 5.0 * res.error, "{:?}", res);
+    assert!(res.error <= 1e-2 * res.value);
+    assert!(res.neval > 10000);
+
+    let res = MonteMiser::new().with_range(range.clone())
+                               .with_seed(1)
+                               .integrate(fun, 1e-3, 1e-12)
+                               .expect("should converge");
+    assert!((res.value - exp).abs() < 5.0 * res.error, "{:?}", res);
+
+    for &mode in [GSLVegasMode::Importance, GSLVegasMode::ImportanceOnly,
+                  GSLVegasMode::Stratified].iter() {
+        let res = MonteVegas::new().with_range(range.clone())
+                                   .with_mode(mode)
+                                   .integrate(fun, 1e-4, 1e-12)
+                                   .expect("should converge");
+        assert!((res.value - exp).abs() < 5.0 * res.error, "{:?}", res);
+        assert!(res.chi2_dof.is_finite());
+    }
+
+    // Without enough calls to meet the tolerance
+    assert_eq!(MontePlain::new().with_maxcalls(20000).integrate(fun, 1e-8, 0.0),
+               Err(GSLIntegrationError::GSLError(GSLErrorCode::MaxIter)));
+    // The range and integrand disagree on the dimension
+    assert_eq!(MonteMiser::new().with_range(range)
+                                .integrate(|(a, b, c): (Real, Real, Real)| a * b * c, 1e-2, 0.0),
+               Err(GSLIntegrationError::InvalidInputDim(3)));
+    assert_eq!(MonteVegas::new().integrate(three_inputs_two_outputs, 1e-2, 0.0),
+               Err(GSLIntegrationError::InvalidOutputDim(2)));
+    assert!(MonteRange::new((0.0, 1.0), (1.0, 0.0)).is_err());
+}
+
+#[test]
+fn test_glfixed_nd() {
+    // Exact for a polynomial of degree 3 in x and 7 in y, over a box
+    let mut gl = GLFixedND::new(2).unwrap()
+                                  .with_n(&[2, 4]).unwrap()
+                                  .with_range(MonteRange::new((0.0, -1.0), (2.0, 1.0)).unwrap());
+    assert_eq!(gl.n(), vec![2, 4]);
+    let res = gl.integrate(|(x, y): (Real, Real)| (x.powi(3) * (y.powi(6) + y.powi(7)), 1.0),
+                           0.0, 0.0)
+                .unwrap();
+    assert_eq!(res.neval, 8);
+    assert_eq!(res.results.len(), 2);
+    assert!((res.results[0].value - 8.0 / 7.0).abs() < 1e-12);
+    assert!((res.results[1].value - 4.0).abs() < 1e-12);
+
+    assert_eq!(gl.integrate(three_inputs_two_outputs, 0.0, 0.0),
+               Err(GSLIntegrationError::InvalidInputDim(3)));
+    let mut gl = gl.without_range().with_n(&[5]).unwrap();
+    let res = gl.integrate(three_inputs_two_outputs, 0.0, 0.0).unwrap();
+    assert_eq!(res.neval, 125);
+    assert!((res.results[0].value - 0.25).abs() < 1e-12);
+
+    match GLFixedND::new(0) {
+        Err(GSLIntegrationError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_iterated() {
+    use ::iterated::{Iterated, IteratedError};
+
+    // The integral of 1 / sqrt(x - y) over the triangle 0 < y < x < 1,
+    // which is singular along the inner axis's upper limit
+    let mut iterated = Iterated::new(QAG::new(100), 0.0, 1.0)
+                               .then(QAGS::new(100), |_| 0.0, |x| x[0]);
+    let res = iterated.integrate(|(x, y): (Real, Real)| (x - y).sqrt().recip(), 1e-8, 1e-10)
+                      .expect("should converge");
+    assert!((res.value - 4.0 / 3.0).abs() < 1e-7, "{:?}", res);
+    assert!(res.error < 1e-6);
+
+    match iterated.integrate(|(x, y): (Real, Real)| (x - y).recip(), 1e-8, 1e-10) {
+        Err(IteratedError::Failed(_, ref err)) => {
+            match err.downcast_ref::<GSLIntegrationError>() {
+                Some(&GSLIntegrationError::GSLError(_)) => (),
+                other => panic!("expected a GSL error, got {:?}", other),
+            }
+        },
+        other => panic!("expected a GSL error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_integrate_dyn() {
+    let mut qags = QAGS::new(100).with_range(0.0, 2.0);
+    let res = qags.integrate_dyn(1, 1, &mut |x, f| f[0] = quadratic_1(x[0]), 1e-10, 1e-12)
+                  .expect("should converge");
+    assert!((res.value - quadratic_1_integral(0.0, 2.0)).abs() < 1e-8);
+    assert_eq!(qags.integrate_dyn(2, 1, &mut |_, f| f[0] = 1.0, 1e-10, 1e-12),
+               Err(GSLIntegrationError::InvalidInputDim(2)));
+    assert_eq!(qags.integrate_dyn(1, 2, &mut |_, f| f[0] = 1.0, 1e-10, 1e-12),
+               Err(GSLIntegrationError::InvalidOutputDim(2)));
+
+    let res = CQUAD::new(100).integrate_dyn(1, 1, &mut |x, f| f[0] = x[0], 1e-10, 1e-12)
+                             .expect("should converge");
+    assert!((res.value - 0.5).abs() < 1e-10);
+}
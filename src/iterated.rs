@@ -0,0 +1,335 @@
+//! Iterated integration: integrating over several dimensions by nesting
+//! one-dimensional integrators.
+//!
+//! The limits of each inner axis may depend on the outer coordinates, so
+//! `Iterated` integrates over regions such as triangles and disks as well as
+//! boxes, with any one-dimensional integrators, such as GSL's `QAG` and
+//! `QAGS`. Each axis may use a different integrator.
+
+use std::{error, fmt};
+
+use super::traits::{IntegrandInput, IntegrandOutput, IntegrationResults, Integrator};
+use super::{IntegrationResult, Real};
+
+/// The error of the integrator of an axis, whatever its type.
+pub type AxisError = Box<dyn error::Error + Send + Sync>;
+
+/// The integrator of an axis, with its type erased, so that each axis may
+/// use a different one.
+trait AxisIntegrator: fmt::Debug {
+    fn integrate_axis(&mut self, fun: &mut dyn FnMut(Real) -> Real, epsrel: Real, epsabs: Real)
+        -> Result<IntegrationResult, AxisError>;
+}
+
+impl<I> AxisIntegrator for I
+    where I: Integrator + fmt::Debug,
+          I::Failure: Send + Sync
+{
+    fn integrate_axis(&mut self, fun: &mut dyn FnMut(Real) -> Real, epsrel: Real, epsabs: Real)
+            -> Result<IntegrationResult, AxisError> {
+        match self.integrate(|t: Real| fun(t), epsrel, epsabs) {
+            Ok(res) => Ok(res.results().next().expect("integrator returned no results")),
+            Err(err) => Err(Box::new(err)),
+        }
+    }
+}
+
+/// The limits of an axis, given the coordinates along the outer axes.
+type Limit = Box<dyn Fn(&[Real]) -> Real>;
+
+struct Axis {
+    integrator: Box<dyn AxisIntegrator>,
+    low: Limit,
+    high: Limit,
+}
+
+#[derive(Debug)]
+pub enum IteratedError {
+    /// The integrand's input has a different number of dimensions than
+    /// there are axes: (input dimensions, axes)
+    BadDim(usize, usize),
+    /// The integrand returned more than one value. Only scalar integrands
+    /// can be integrated.
+    BadComp(usize),
+    /// The integrator of the given axis (counting from the outermost, 0)
+    /// failed. The error is that of the axis's integrator, which can be
+    /// recovered with `downcast_ref`.
+    Failed(usize, AxisError),
+}
+
+impl fmt::Display for IteratedError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IteratedError::BadDim(ndim, naxes) =>
+                write!(fmt, "integrand has {} dimensions, but there are {} axes", ndim, naxes),
+            IteratedError::BadComp(ncomp) =>
+                write!(fmt, "integrand returned {} values, rather than 1", ncomp),
+            IteratedError::Failed(axis, ref err) =>
+                write!(fmt, "integration over axis {} failed: {}", axis, err),
+        }
+    }
+}
+
+impl error::Error for IteratedError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            IteratedError::Failed(_, ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+/// Integrates over several dimensions by integrating over each axis in
+/// turn with a one-dimensional integrator, from the outermost axis to the
+/// innermost. The limits of each axis may be functions of the coordinates
+/// along the axes outside it, so for example,
+///
+/// ```text
+/// \int_a^b dx \int_{y_low(x)}^{y_high(x)} dy f(x, y)
+/// ```
+///
+/// integrates `f` over the region between the curves `y_low` and `y_high`.
+///
+/// Each axis has its own integrator, of any type whose errors are `Send`
+/// and `Sync`, so for example a singular inner axis can use `QAGS` inside a
+/// smooth outer axis integrated with `QAG`. Each axis is mapped onto
+/// [0, 1], which its integrator must integrate over, as GSL's integrators
+/// and `native::GaussKronrod` do by default. Every axis is integrated to the
+/// same tolerance. The error is the error of the outermost integration, plus
+/// a bound on what the errors of the inner integrations add to it.
+///
+/// ```
+/// use integrators::{Integrator, Real2};
+/// use integrators::iterated::Iterated;
+/// # #[cfg(feature = "gsl")] {
+/// use integrators::gsl::{QAG, QAGRule, QAGS};
+///
+/// // The integral of x y over the triangle under y = x, for x from 0 to 1
+/// let res = Iterated::new(QAG::new(100).with_rule(QAGRule::Gauss21), 0.0, 1.0)
+///                    .then(QAGS::new(100), |_| 0.0, |outer| outer[0])
+///                    .integrate(|(x, y): Real2| x * y, 1e-10, 1e-12)
+///                    .unwrap();
+/// assert!((res.value - 0.125).abs() < 1e-10);
+/// # }
+/// ```
+pub struct Iterated {
+    axes: Vec<Axis>,
+}
+
+impl fmt::Debug for Iterated {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let integrators: Vec<&dyn AxisIntegrator> = self.axes.iter().map(|axis| &*axis.integrator).collect();
+        fmt.debug_struct("Iterated")
+           .field("integrators", &integrators)
+           .finish()
+    }
+}
+
+impl Iterated {
+    /// Integrates the outermost axis from `low` to `high` with `integrator`.
+    pub fn new<I>(integrator: I, low: Real, high: Real) -> Self
+        where I: Integrator + fmt::Debug + 'static,
+              I::Failure: Send + Sync
+    {
+        Iterated {
+            axes: vec![Axis {
+                integrator: Box::new(integrator),
+                low: Box::new(move |_| low),
+                high: Box::new(move |_| high),
+            }],
+        }
+    }
+
+    /// Adds an axis inside the others, integrated from `low(outer)` to
+    /// `high(outer)` with `integrator`, where `outer` holds the coordinates
+    /// along the outer axes, from the outermost. The integrator need not be
+    /// of the same type as those of the other axes.
+    pub fn then<I, L, H>(mut self, integrator: I, low: L, high: H) -> Self
+        where I: Integrator + fmt::Debug + 'static,
+              I::Failure: Send + Sync,
+              L: Fn(&[Real]) -> Real + 'static,
+              H: Fn(&[Real]) -> Real + 'static
+    {
+        self.axes.push(Axis {
+            integrator: Box::new(integrator),
+            low: Box::new(low),
+            high: Box::new(high),
+        });
+        self
+    }
+
+    /// The number of axes, which is the number of dimensions of the
+    /// integrand.
+    pub fn ndim(&self) -> usize {
+        self.axes.len()
+    }
+}
+
+/// Integrates over the first of `axes`, and the rest inside it, at the
+/// point whose outer coordinates are `point`. On failure, returns `None`
+/// after storing the error in `failure`, if it does not already hold one.
+fn integrate_axis<F>(axes: &mut [Axis], point: &mut Vec<Real>, fun: &mut F,
+                     failure: &mut Option<IteratedError>,
+                     epsrel: Real, epsabs: Real) -> Option<IntegrationResult>
+    where F: FnMut(&[Real]) -> Result<Real, IteratedError>
+{
+    let (axis, inner) = axes.split_first_mut().expect("there is at least one axis");
+    let depth = point.len();
+    let low = (axis.low)(point);
+    let width = (axis.high)(point) - low;
+
+    // The largest error of the inner integrations, scaled by the Jacobian,
+    // bounds what they add to the error of this one.
+    let mut inner_error: Real = 0.0;
+    let res = axis.integrator.integrate_axis(&mut |t: Real| {
+        if failure.is_some() {
+            return 0.0;
+        }
+        point.truncate(depth);
+        point.push(low + width * t);
+        let value = if inner.is_empty() {
+            match fun(point) {
+                Ok(value) => value,
+                Err(err) => {
+                    *failure = Some(err);
+                    return 0.0;
+                },
+            }
+        } else {
+            match integrate_axis(inner, point, fun, failure, epsrel, epsabs) {
+                Some(res) => {
+                    inner_error = inner_error.max((width * res.error).abs());
+                    res.value
+                },
+                None => return 0.0,
+            }
+        };
+        width * value
+    }, epsrel, epsabs);
+
+    match res {
+        Ok(res) => {
+            if failure.is_some() {
+                return None;
+            }
+            Some(IntegrationResult {
+                value: res.value,
+                error: res.error + inner_error,
+            })
+        },
+        Err(err) => {
+            if failure.is_none() {
+                *failure = Some(IteratedError::Failed(depth, err));
+            }
+            None
+        },
+    }
+}
+
+impl Integrator for Iterated {
+    type Success = IntegrationResult;
+    type Failure = IteratedError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if A::input_size() != self.axes.len() {
+            return Err(IteratedError::BadDim(A::input_size(), self.axes.len()));
+        }
+
+        let mut call = |x: &[Real]| {
+            let output = fun(A::from_args(x));
+            if output.output_size() != 1 {
+                return Err(IteratedError::BadComp(output.output_size()));
+            }
+            let mut value = [0.0];
+            output.into_args(&mut value);
+            Ok(value[0])
+        };
+        let mut failure = None;
+        let mut point = Vec::with_capacity(self.axes.len());
+        let res = integrate_axis(&mut self.axes, &mut point, &mut call, &mut failure,
+                                 epsrel, epsabs);
+        match failure {
+            Some(err) => Err(err),
+            None => Ok(res.expect("integration without failure has a result")),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "native")]
+mod test_iterated {
+    use std::f64::consts::PI;
+    use std::panic;
+
+    use super::{Iterated, IteratedError};
+    use ::{Integrator, Real, Real2, Real3};
+    use ::native::{GaussKronrod, NativeError, TanhSinh};
+
+    #[test]
+    fn test_iterated() {
+        // The area of the unit disk, and its second moment
+        let mut disk = Iterated::new(GaussKronrod::new(100), -1.0, 1.0)
+                                .then(GaussKronrod::new(100),
+                                      |x| -(1.0 - x[0] * x[0]).sqrt(),
+                                      |x| (1.0 - x[0] * x[0]).sqrt());
+        let res = disk.integrate(|_: Real2| 1.0, 1e-10, 1e-12).unwrap();
+        assert!((res.value - PI).abs() < 1e-8, "{:?}", res);
+        assert!(res.error < 1e-8);
+        let res = disk.integrate(|(x, y): Real2| x * x + y * y, 1e-10, 1e-12).unwrap();
+        assert!((res.value - PI / 2.0).abs() < 1e-8, "{:?}", res);
+
+        // The tetrahedron x + y + z < 1, whose limits depend on two outer
+        // coordinates
+        let mut tetrahedron = Iterated::new(GaussKronrod::new(100), 0.0, 1.0)
+                                       .then(GaussKronrod::new(100), |_| 0.0, |x| 1.0 - x[0])
+                                       .then(GaussKronrod::new(100), |_| 0.0, |x| 1.0 - x[0] - x[1]);
+        assert_eq!(tetrahedron.ndim(), 3);
+        let res = tetrahedron.integrate(|(x, y, z): Real3| x * y * z, 1e-10, 1e-14).unwrap();
+        assert!((res.value - 1.0 / 720.0).abs() < 1e-12, "{:?}", res);
+
+        match tetrahedron.integrate(|(x, _): Real2| x, 1e-10, 1e-12) {
+            Err(IteratedError::BadDim(2, 3)) => (),
+            other => panic!("expected bad dimensions, got {:?}", other),
+        }
+        match disk.integrate(|(x, y): Real2| (x, y), 1e-10, 1e-12) {
+            Err(IteratedError::BadComp(2)) => (),
+            other => panic!("expected bad components, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_iterated_mixed() {
+        // The integral of 1 / sqrt(x - y) over the triangle 0 < y < x < 1,
+        // whose inner axis is singular at its upper limit, so is given to
+        // tanh-sinh quadrature, inside a smooth Gauss-Kronrod outer axis
+        let mut iterated = Iterated::new(GaussKronrod::new(100), 0.0, 1.0)
+                                   .then(TanhSinh::new(10), |_| 0.0, |x| x[0]);
+        let res = iterated.integrate(|(x, y): Real2| (x - y).sqrt().recip(), 1e-8, 1e-10)
+                          .unwrap();
+        assert!((res.value - 4.0 / 3.0).abs() < 1e-7, "{:?}", res);
+        assert!(format!("{:?}", iterated).contains("TanhSinh"));
+    }
+
+    #[test]
+    fn test_iterated_failure() {
+        // The inner integrand is singular at y = x, which the inner
+        // integrator cannot resolve with so few subintervals
+        let mut iterated = Iterated::new(GaussKronrod::new(100), 0.0, 1.0)
+                                   .then(GaussKronrod::new(2), |_| 0.0, |x| x[0]);
+        match iterated.integrate(|(x, y): Real2| (x - y).abs().powf(-0.9), 1e-10, 1e-12) {
+            Err(IteratedError::Failed(1, ref err)) => {
+                match err.downcast_ref::<NativeError>() {
+                    Some(&NativeError::DidNotConverge(_)) => (),
+                    other => panic!("expected non-convergence, got {:?}", other),
+                }
+            },
+            other => panic!("expected inner failure, got {:?}", other),
+        }
+        let panicked = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            iterated.integrate(|(x, _): Real2| -> Real { panic!("at {}", x) }, 1e-10, 1e-12)
+        }));
+        assert!(panicked.is_err());
+    }
+}
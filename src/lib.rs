@@ -1,5 +1,7 @@
 pub mod traits;
 pub mod ffi;
+pub mod domain;
+pub mod iterated;
 
 #[cfg(any(feature = "cuba", feature = "gsl"))]
 mod bindings;
@@ -10,6 +12,9 @@ pub mod cuba;
 #[cfg(feature = "gsl")]
 pub mod gsl;
 
+#[cfg(feature = "native")]
+pub mod native;
+
 #[cfg(test)]
 mod test;
 
@@ -21,8 +26,10 @@ pub type Real5 = (Real, Real, Real, Real, Real);
 pub type Real6 = (Real, Real, Real, Real, Real, Real);
 pub type Real7 = (Real, Real, Real, Real, Real, Real, Real);
 pub type Real8 = (Real, Real, Real, Real, Real, Real, Real, Real);
+/// An integrand input or output of any number of values.
+pub type RealN<const N: usize> = [Real; N];
 
-pub use traits::{Integrator, IntegrandInput, IntegrandOutput,
+pub use traits::{Integrator, DynIntegrator, IntegrandInput, IntegrandOutput,
                  IntegrationResults};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -0,0 +1,376 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64;
+
+use ::{IntegrationResult, Integrator, Real};
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::kronrod::{WG7, WGK15, XGK15};
+use super::{Integrand, NativeError, NativeIntegrationResults};
+
+/// The largest number of dimensions supported. Each application of the
+/// Genz-Malik rule evaluates the integrand at the `2^n` corners of a
+/// scaled hypercube, which is prohibitive well before this.
+pub const CUBATURE_MAX_DIM: usize = 30;
+
+/// Distances of the nodes of the Genz-Malik rule from the center of a
+/// region, relative to its half-widths: `sqrt(9/70)`, `sqrt(9/10)` and
+/// `sqrt(9/19)`.
+const LAMBDA2: Real = 0.35856858280031806;
+const LAMBDA4: Real = 0.9486832980505138;
+const LAMBDA5: Real = 0.6882472016116853;
+
+/// Weights of the Genz-Malik rule which do not depend on the number of
+/// dimensions, for the degree 7 rule and its embedded degree 5 rule.
+const WEIGHT2: Real = 980.0 / 6561.0;
+const WEIGHT4: Real = 200.0 / 19683.0;
+const WEIGHT_E2: Real = 245.0 / 486.0;
+const WEIGHT_E4: Real = 25.0 / 729.0;
+
+/// The estimate of a rule over one region, for each component of the
+/// integrand, with the axis along which to split the region.
+struct Estimate {
+    value: Vec<Real>,
+    error: Vec<Real>,
+    split: usize,
+}
+
+/// Sums `f` into `sum`, elementwise.
+fn accumulate(sum: &mut [Real], f: &[Real]) {
+    for (s, &f) in sum.iter_mut().zip(f.iter()) {
+        *s += f;
+    }
+}
+
+/// Applies the 15-point Gauss-Kronrod rule, with the embedded 7-point Gauss
+/// rule for the error, to a 1 dimensional region.
+fn apply_gauss_kronrod<A, B, F>(fun: &mut Integrand<A, B, F>,
+                                center: Real, halfwidth: Real) -> Estimate
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    let ncomp = fun.ncomp;
+    let mut f = vec![0.0; ncomp];
+    let mut kronrod = vec![0.0; ncomp];
+    let mut gauss = vec![0.0; ncomp];
+    let n = XGK15.len();
+    for (j, (&x, &wgk)) in XGK15.iter().zip(WGK15.iter()).enumerate() {
+        let nodes = if j == n - 1 { 1 } else { 2 };
+        for &sign in [1.0, -1.0].iter().take(nodes) {
+            fun.call(&[center + sign * halfwidth * x], &mut f);
+            for c in 0..ncomp {
+                kronrod[c] += wgk * f[c];
+                if j % 2 == 1 {
+                    gauss[c] += WG7[j / 2] * f[c];
+                }
+            }
+        }
+    }
+    Estimate {
+        value: kronrod.iter().map(|k| k * halfwidth).collect(),
+        error: kronrod.iter().zip(gauss.iter())
+                      .map(|(k, g)| ((k - g) * halfwidth).abs())
+                      .collect(),
+        split: 0,
+    }
+}
+
+/// Applies the degree 7 Genz-Malik rule, with its embedded degree 5 rule
+/// for the error, to a region of 2 or more dimensions. The region is to be
+/// split along the axis where the integrand's fourth difference is largest.
+fn apply_genz_malik<A, B, F>(fun: &mut Integrand<A, B, F>,
+                             center: &[Real], halfwidth: &[Real]) -> Estimate
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    let ndim = center.len();
+    let ncomp = fun.ncomp;
+    let n = ndim as Real;
+    let weight1 = (12824.0 - 9120.0 * n + 400.0 * n * n) / 19683.0;
+    let weight3 = (1820.0 - 400.0 * n) / 19683.0;
+    let weight5 = 6859.0 / 19683.0 / (1u64 << ndim) as Real;
+    let weight_e1 = (729.0 - 950.0 * n + 50.0 * n * n) / 729.0;
+    let weight_e3 = (265.0 - 100.0 * n) / 1458.0;
+    let ratio = (LAMBDA2 / LAMBDA4).powi(2);
+
+    let mut x = center.to_vec();
+    let mut f = vec![0.0; ncomp];
+    let mut f0 = vec![0.0; ncomp];
+    let mut sum2 = vec![0.0; ncomp];
+    let mut sum3 = vec![0.0; ncomp];
+    let mut sum4 = vec![0.0; ncomp];
+    let mut sum5 = vec![0.0; ncomp];
+    let mut pair2 = vec![0.0; ncomp];
+    let mut pair3 = vec![0.0; ncomp];
+
+    fun.call(&x, &mut f0);
+
+    let mut split = 0;
+    let mut max_diff = -1.0;
+    for i in 0..ndim {
+        for (&lambda, pair) in [LAMBDA2, LAMBDA4].iter().zip([&mut pair2, &mut pair3].iter_mut()) {
+            for p in pair.iter_mut() {
+                *p = 0.0;
+            }
+            for &sign in [1.0, -1.0].iter() {
+                x[i] = center[i] + sign * lambda * halfwidth[i];
+                fun.call(&x, &mut f);
+                accumulate(pair, &f);
+            }
+        }
+        x[i] = center[i];
+        accumulate(&mut sum2, &pair2);
+        accumulate(&mut sum3, &pair3);
+
+        let diff: Real = (0..ncomp).map(|c| {
+            (pair2[c] - 2.0 * f0[c] - ratio * (pair3[c] - 2.0 * f0[c])).abs()
+        }).sum();
+        // Ties, such as for integrands of low degree, go to the widest axis.
+        if (diff > max_diff) || ((diff == max_diff) && (halfwidth[i] > halfwidth[split])) {
+            max_diff = diff;
+            split = i;
+        }
+    }
+
+    for i in 0..ndim {
+        for j in i + 1..ndim {
+            for &(si, sj) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)].iter() {
+                x[i] = center[i] + si * LAMBDA4 * halfwidth[i];
+                x[j] = center[j] + sj * LAMBDA4 * halfwidth[j];
+                fun.call(&x, &mut f);
+                accumulate(&mut sum4, &f);
+            }
+            x[j] = center[j];
+        }
+        x[i] = center[i];
+    }
+
+    for corner in 0..(1u64 << ndim) {
+        for (i, x) in x.iter_mut().enumerate() {
+            let sign = if corner & (1 << i) != 0 { 1.0 } else { -1.0 };
+            *x = center[i] + sign * LAMBDA5 * halfwidth[i];
+        }
+        fun.call(&x, &mut f);
+        accumulate(&mut sum5, &f);
+    }
+
+    let volume: Real = halfwidth.iter().map(|h| 2.0 * h).product();
+    let mut est = Estimate {
+        value: Vec::with_capacity(ncomp),
+        error: Vec::with_capacity(ncomp),
+        split,
+    };
+    for c in 0..ncomp {
+        let degree7 = weight1 * f0[c] + WEIGHT2 * sum2[c] + weight3 * sum3[c]
+            + WEIGHT4 * sum4[c] + weight5 * sum5[c];
+        let degree5 = weight_e1 * f0[c] + WEIGHT_E2 * sum2[c] + weight_e3 * sum3[c]
+            + WEIGHT_E4 * sum4[c];
+        est.value.push(volume * degree7);
+        est.error.push((volume * (degree7 - degree5)).abs());
+    }
+    est
+}
+
+fn apply_rule<A, B, F>(fun: &mut Integrand<A, B, F>,
+                       center: &[Real], halfwidth: &[Real]) -> Estimate
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    if center.len() == 1 {
+        apply_gauss_kronrod(fun, center[0], halfwidth[0])
+    } else {
+        apply_genz_malik(fun, center, halfwidth)
+    }
+}
+
+/// The number of evaluations of each application of the rule in `ndim`
+/// dimensions.
+fn rule_neval(ndim: usize) -> usize {
+    if ndim == 1 {
+        XGK15.len() * 2 - 1
+    } else {
+        1 + 4 * ndim + 2 * ndim * (ndim - 1) + (1 << ndim)
+    }
+}
+
+/// A subregion, ordered by its largest error estimate over all components,
+/// so that the worst is split first.
+#[derive(Debug)]
+struct Region {
+    center: Vec<Real>,
+    halfwidth: Vec<Real>,
+    value: Vec<Real>,
+    error: Vec<Real>,
+    split: usize,
+    priority: Real,
+}
+
+impl Region {
+    fn new(center: Vec<Real>, halfwidth: Vec<Real>, est: Estimate) -> Self {
+        // NaN errors come first, so they are not left unresolved.
+        let priority = est.error.iter().fold(0.0, |max: Real, &err| {
+            if err.is_nan() { f64::INFINITY } else { max.max(err) }
+        });
+        Region {
+            center, halfwidth, priority,
+            value: est.value,
+            error: est.error,
+            split: est.split,
+        }
+    }
+}
+
+impl PartialEq for Region {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Region {}
+
+impl PartialOrd for Region {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Region {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.partial_cmp(&other.priority)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Adaptive cubature over the unit hypercube, in pure Rust, for integrands
+/// of any number of dimensions from 1 to `CUBATURE_MAX_DIM`. The region is
+/// repeatedly bisected, always at the subregion with the largest error
+/// estimate, until the total error of every component of the integrand
+/// meets the requested tolerance.
+///
+/// Subregions of 2 or more dimensions are integrated with the degree 7 rule
+/// of Genz and Malik, whose embedded degree 5 rule estimates the error, and
+/// are bisected along the axis where the integrand varies most. This is the
+/// same approach as Cuba's `Cuhre`, but needs no C library. In 1 dimension,
+/// the 15-point Gauss-Kronrod rule is used instead.
+///
+/// Each application of the rule evaluates the integrand `2^n + 2n^2 + 2n +
+/// 1` times in `n` dimensions, so this is best for smooth integrands of up
+/// to about 7 dimensions.
+///
+/// ```
+/// use integrators::{native, Integrator, Real3};
+///
+/// let res = native::Cubature::new(100000)
+///                            .integrate(|(x, y, z): Real3| (x + y + z).exp(), 1e-10, 1e-12)
+///                            .unwrap();
+/// let exp = (::std::f64::consts::E - 1.0).powi(3);
+/// assert!((res.results[0].value - exp).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cubature {
+    maxeval: usize,
+}
+
+impl Cubature {
+    /// Creates a new `Cubature` which may evaluate the integrand up to
+    /// `maxeval` times.
+    pub fn new(maxeval: usize) -> Self {
+        Cubature { maxeval }
+    }
+
+    /// Set the maximum number of evaluations.
+    pub fn with_maxeval(self, maxeval: usize) -> Self {
+        Cubature { maxeval }
+    }
+
+    pub fn maxeval(&self) -> usize {
+        self.maxeval
+    }
+}
+
+impl Integrator for Cubature {
+    type Success = NativeIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let ndim = A::input_size();
+        if (ndim == 0) | (ndim > CUBATURE_MAX_DIM) {
+            return Err(NativeError::BadDim("cubature", ndim));
+        }
+        let nrule = rule_neval(ndim);
+        if self.maxeval < nrule + 1 {
+            return Err(NativeError::InvalidParameter(
+                "cubature needs enough evaluations to apply its rule at least once"));
+        }
+
+        let mut fun = Integrand::new("cubature", fun, &vec![0.5; ndim])?;
+        let ncomp = fun.ncomp;
+        let tolerance = |value: &[Real], c: usize| epsabs.max(epsrel * value[c].abs());
+
+        let (center, halfwidth) = (vec![0.5; ndim], vec![0.5; ndim]);
+        let first = apply_rule(&mut fun, &center, &halfwidth);
+        let mut value = first.value.clone();
+        let mut error = first.error.clone();
+        let mut regions = BinaryHeap::new();
+        regions.push(Region::new(center, halfwidth, first));
+
+        let mut failed = false;
+        while !(0..ncomp).all(|c| error[c] <= tolerance(&value, c)) {
+            if fun.neval + 2 * nrule > self.maxeval {
+                failed = true;
+                break;
+            }
+            let worst = regions.pop().expect("there is at least one region");
+            let axis = worst.split;
+            let h = 0.5 * worst.halfwidth[axis];
+            if h <= f64::EPSILON * worst.center[axis].abs() {
+                // Too small to split any further
+                regions.push(worst);
+                failed = true;
+                break;
+            }
+
+            let mut halfwidth = worst.halfwidth.clone();
+            halfwidth[axis] = h;
+            for &sign in [-1.0, 1.0].iter() {
+                let mut center = worst.center.clone();
+                center[axis] += sign * h;
+                let est = apply_rule(&mut fun, &center, &halfwidth);
+                accumulate(&mut value, &est.value);
+                accumulate(&mut error, &est.error);
+                regions.push(Region::new(center, halfwidth.clone(), est));
+            }
+            for c in 0..ncomp {
+                value[c] -= worst.value[c];
+                error[c] -= worst.error[c];
+            }
+        }
+
+        // Sum the subregions afresh, rather than trusting the running totals.
+        let mut value = vec![0.0; ncomp];
+        let mut error = vec![0.0; ncomp];
+        for region in regions.iter() {
+            accumulate(&mut value, &region.value);
+            accumulate(&mut error, &region.error);
+        }
+        let converged = !failed & (0..ncomp).all(|c| error[c] <= tolerance(&value, c));
+        let results = NativeIntegrationResults {
+            nregions: Some(regions.len()),
+            neval: fun.neval,
+            results: value.into_iter().zip(error)
+                          .map(|(value, error)| IntegrationResult { value, error })
+                          .collect(),
+        };
+
+        if converged {
+            Ok(results)
+        } else {
+            Err(NativeError::DidNotConverge(results))
+        }
+    }
+}
@@ -0,0 +1,364 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64;
+
+use ::{IntegrationResult, Integrator, Real};
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::kronrod::*;
+use super::{Integrand, NativeError, NativeIntegrationResults};
+
+/// Gauss-Kronrod rule to apply on each subinterval. Each rule pairs an
+/// `n`-point Gauss rule with the `2n + 1`-point Kronrod rule which extends
+/// it; the difference between the two gives the error estimate.
+#[derive(Debug, Hash, Copy, Clone, PartialEq, Eq)]
+pub enum GaussKronrodRule {
+    G7K15,
+    G10K21,
+    G15K31,
+    G20K41,
+    G25K51,
+    G30K61
+}
+
+impl GaussKronrodRule {
+    /// The Kronrod nodes and weights, and the Gauss weights.
+    fn tables(&self) -> (&'static [Real], &'static [Real], &'static [Real]) {
+        use self::GaussKronrodRule::*;
+        match *self {
+            G7K15 => (&XGK15, &WGK15, &WG7),
+            G10K21 => (&XGK21, &WGK21, &WG10),
+            G15K31 => (&XGK31, &WGK31, &WG15),
+            G20K41 => (&XGK41, &WGK41, &WG20),
+            G25K51 => (&XGK51, &WGK51, &WG25),
+            G30K61 => (&XGK61, &WGK61, &WG30),
+        }
+    }
+
+    /// The number of integrand evaluations per application of the rule.
+    pub fn npoints(&self) -> usize {
+        2 * self.tables().0.len() - 1
+    }
+}
+
+/// The result of applying a rule to one subinterval, for each component of
+/// the integrand.
+#[derive(Debug)]
+struct Estimate {
+    value: Vec<Real>,
+    error: Vec<Real>,
+    /// The integral of the absolute value of the integrand.
+    resabs: Vec<Real>,
+    /// The integral of the absolute difference between the integrand and its
+    /// mean.
+    resasc: Vec<Real>,
+}
+
+/// Scales the raw difference between the Gauss and Kronrod estimates into
+/// an error estimate, as QUADPACK does.
+fn rescale_error(err: Real, resabs: Real, resasc: Real) -> Real {
+    let mut err = err.abs();
+    if (resasc != 0.0) & (err != 0.0) {
+        err = resasc * (200.0 * err / resasc).powf(1.5).min(1.0);
+    }
+    if resabs > f64::MIN_POSITIVE / (50.0 * f64::EPSILON) {
+        err = err.max(50.0 * f64::EPSILON * resabs);
+    }
+    err
+}
+
+fn apply_rule<A, B, F>(rule: GaussKronrodRule, fun: &mut Integrand<A, B, F>,
+                       low: Real, high: Real) -> Estimate
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    let (xgk, wgk, wg) = rule.tables();
+    let n = xgk.len();
+    let ncomp = fun.ncomp;
+    let center = 0.5 * (low + high);
+    let half_length = 0.5 * (high - low);
+
+    let mut fc = vec![0.0; ncomp];
+    fun.call(&[center], &mut fc);
+    let mut fv1 = vec![0.0; (n - 1) * ncomp];
+    let mut fv2 = vec![0.0; (n - 1) * ncomp];
+    for j in 0..n - 1 {
+        let abscissa = half_length * xgk[j];
+        fun.call(&[center - abscissa], &mut fv1[j * ncomp..(j + 1) * ncomp]);
+        fun.call(&[center + abscissa], &mut fv2[j * ncomp..(j + 1) * ncomp]);
+    }
+
+    let mut est = Estimate {
+        value: Vec::with_capacity(ncomp),
+        error: Vec::with_capacity(ncomp),
+        resabs: Vec::with_capacity(ncomp),
+        resasc: Vec::with_capacity(ncomp),
+    };
+    for c in 0..ncomp {
+        // The center is a node of the Gauss rule only if it has an odd
+        // number of points.
+        let mut gauss = if n % 2 == 0 { fc[c] * wg[n / 2 - 1] } else { 0.0 };
+        let mut kronrod = fc[c] * wgk[n - 1];
+        let mut resabs = kronrod.abs();
+        for j in 0..n - 1 {
+            let (f1, f2) = (fv1[j * ncomp + c], fv2[j * ncomp + c]);
+            if j % 2 == 1 {
+                gauss += wg[j / 2] * (f1 + f2);
+            }
+            kronrod += wgk[j] * (f1 + f2);
+            resabs += wgk[j] * (f1.abs() + f2.abs());
+        }
+
+        let mean = 0.5 * kronrod;
+        let mut resasc = wgk[n - 1] * (fc[c] - mean).abs();
+        for j in 0..n - 1 {
+            let (f1, f2) = (fv1[j * ncomp + c], fv2[j * ncomp + c]);
+            resasc += wgk[j] * ((f1 - mean).abs() + (f2 - mean).abs());
+        }
+
+        let (resabs, resasc) = (resabs * half_length.abs(),
+                                resasc * half_length.abs());
+        est.value.push(kronrod * half_length);
+        est.error.push(rescale_error((kronrod - gauss) * half_length,
+                                     resabs, resasc));
+        est.resabs.push(resabs);
+        est.resasc.push(resasc);
+    }
+    est
+}
+
+/// A subinterval, ordered by its largest error estimate over all
+/// components, so that the worst is bisected first.
+#[derive(Debug)]
+struct Interval {
+    low: Real,
+    high: Real,
+    value: Vec<Real>,
+    error: Vec<Real>,
+    priority: Real,
+}
+
+impl Interval {
+    fn new(low: Real, high: Real, est: Estimate) -> Self {
+        // NaN errors come first, so they are not left unresolved.
+        let priority = est.error.iter().fold(0.0, |max: Real, &err| {
+            if err.is_nan() { f64::INFINITY } else { max.max(err) }
+        });
+        Interval {
+            low, high, priority,
+            value: est.value,
+            error: est.error,
+        }
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+
+impl Eq for Interval {}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.partial_cmp(&other.priority)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Whether an interval is too small to bisect any further.
+fn too_small(low: Real, mid: Real, high: Real) -> bool {
+    let tmp = (1.0 + 100.0 * f64::EPSILON) * (mid.abs() + 1000.0 * f64::MIN_POSITIVE);
+    (low.abs() <= tmp) & (high.abs() <= tmp)
+}
+
+/// Adaptive Gauss-Kronrod integration, in pure Rust. This is the same
+/// algorithm as GSL's QAG (and QUADPACK's QAG): the range is repeatedly
+/// bisected, always at the subinterval with the largest error estimate,
+/// until the total error meets the requested tolerance. Subintervals are
+/// kept in a priority queue ordered by their error.
+///
+/// Integrands with several outputs are supported; every subinterval is
+/// integrated for all outputs at once, and the integration converges when
+/// every output meets the tolerance.
+///
+/// ```
+/// use integrators::{native, Integrator, Real};
+/// let mut gk = native::GaussKronrod::new(1000);
+///
+/// let res1 = gk.integrate(|a: Real| a * a, 1e-6, 1e-10)
+///              .unwrap();
+/// assert!((res1.results[0].value - 3f64.recip()).abs() < res1.results[0].error);
+///
+/// let res2 = gk.with_range(3.0, 10.0)
+///              .with_rule(native::GaussKronrodRule::G7K15)
+///              .integrate(|a: Real| (a, a * a), 1e-6, 1e-10)
+///              .unwrap();
+/// assert!((res2.results[0].value - (100f64 - 9f64) / 2.).abs() < 1e-10);
+/// assert!((res2.results[1].value - (1000f64 - 27f64) / 3.).abs() < 1e-10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GaussKronrod {
+    range_low: Real,
+    range_high: Real,
+    nintervals: usize,
+    rule: GaussKronrodRule
+}
+
+impl GaussKronrod {
+    /// Creates a new `GaussKronrod` which may use up to `nintervals`
+    /// subintervals. This will integrate the range [0, 1], using the
+    /// 61-point (highest-order) Gauss-Kronrod rule. To change the integration
+    /// bounds, see `with_range`, and to change the quadrature rule, see
+    /// `with_rule`.
+    pub fn new(nintervals: usize) -> Self {
+        GaussKronrod {
+            range_low: 0.0,
+            range_high: 1.0,
+            nintervals,
+            rule: GaussKronrodRule::G30K61
+        }
+    }
+
+    /// Set the maximum number of subintervals.
+    pub fn with_nintervals(self, nintervals: usize) -> Self {
+        GaussKronrod { nintervals, ..self }
+    }
+
+    /// Use a different integration range. (Default = [0, 1])
+    pub fn with_range(self, range_low: Real, range_high: Real) -> Self {
+        GaussKronrod { range_low, range_high, ..self }
+    }
+
+    /// Use a specific quadrature rule. (Default = `GaussKronrodRule::G30K61`)
+    pub fn with_rule(self, rule: GaussKronrodRule) -> Self {
+        GaussKronrod { rule, ..self }
+    }
+
+    pub fn range(&self) -> (Real, Real) {
+        (self.range_low, self.range_high)
+    }
+
+    pub fn rule(&self) -> GaussKronrodRule {
+        self.rule
+    }
+}
+
+impl Integrator for GaussKronrod {
+    type Success = NativeIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if A::input_size() != 1 {
+            return Err(NativeError::BadDim("gauss-kronrod", A::input_size()));
+        }
+        if self.nintervals == 0 {
+            return Err(NativeError::InvalidParameter(
+                "gauss-kronrod needs at least one subinterval"));
+        }
+        if (epsabs <= 0.0) & ((epsrel < 50.0 * f64::EPSILON) | (epsrel < 0.5e-28)) {
+            return Err(NativeError::InvalidParameter(
+                "tolerance cannot be achieved with the given epsabs and epsrel"));
+        }
+
+        let (low, high) = (self.range_low, self.range_high);
+        let mut fun = Integrand::new("gauss-kronrod", fun, &[0.5 * (low + high)])?;
+        let ncomp = fun.ncomp;
+        let tolerance = |area: &[Real], c: usize| epsabs.max(epsrel * area[c].abs());
+
+        let first = apply_rule(self.rule, &mut fun, low, high);
+        let mut area = first.value.clone();
+        let mut errsum = first.error.clone();
+
+        let mut failed = false;
+        let mut done = true;
+        for c in 0..ncomp {
+            let round_off = 50.0 * f64::EPSILON * first.resabs[c];
+            if (first.error[c] <= round_off) & (first.error[c] > tolerance(&area, c)) {
+                failed = true;
+            }
+            if !(((first.error[c] <= tolerance(&area, c)) & (first.error[c] != first.resasc[c]))
+                 | (first.error[c] == 0.0)) {
+                done = false;
+            }
+        }
+
+        let mut intervals = BinaryHeap::with_capacity(self.nintervals);
+        intervals.push(Interval::new(low, high, first));
+
+        if !done & !failed & (self.nintervals > 1) {
+            let (mut roundoff_type1, mut roundoff_type2) = (0, 0);
+            loop {
+                let worst = intervals.pop().expect("there is at least one interval");
+                let mid = 0.5 * (worst.low + worst.high);
+                let left = apply_rule(self.rule, &mut fun, worst.low, mid);
+                let right = apply_rule(self.rule, &mut fun, mid, worst.high);
+
+                let (mut type1, mut type2) = (false, false);
+                for c in 0..ncomp {
+                    let area12 = left.value[c] + right.value[c];
+                    let error12 = left.error[c] + right.error[c];
+                    errsum[c] += error12 - worst.error[c];
+                    area[c] += area12 - worst.value[c];
+
+                    if (left.resasc[c] != left.error[c]) & (right.resasc[c] != right.error[c]) {
+                        let delta = worst.value[c] - area12;
+                        if (delta.abs() <= 1e-5 * area12.abs()) & (error12 >= 0.99 * worst.error[c]) {
+                            type1 = true;
+                        }
+                        if (intervals.len() >= 10) & (error12 > worst.error[c]) {
+                            type2 = true;
+                        }
+                    }
+                }
+                roundoff_type1 += type1 as usize;
+                roundoff_type2 += type2 as usize;
+
+                intervals.push(Interval::new(worst.low, mid, left));
+                intervals.push(Interval::new(mid, worst.high, right));
+
+                if (0..ncomp).all(|c| errsum[c] <= tolerance(&area, c)) {
+                    break;
+                }
+                if (roundoff_type1 >= 6) | (roundoff_type2 >= 20)
+                    | too_small(worst.low, mid, worst.high)
+                    | (intervals.len() >= self.nintervals) {
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        // Sum the subintervals afresh, rather than trusting the running total.
+        let mut value = vec![0.0; ncomp];
+        for interval in intervals.iter() {
+            for (total, &v) in value.iter_mut().zip(interval.value.iter()) {
+                *total += v;
+            }
+        }
+        let converged = !failed & (0..ncomp).all(|c| errsum[c] <= tolerance(&value, c));
+        let results = NativeIntegrationResults {
+            nregions: Some(intervals.len()),
+            neval: fun.neval,
+            results: value.into_iter().zip(errsum)
+                          .map(|(value, error)| IntegrationResult { value, error })
+                          .collect(),
+        };
+
+        if converged {
+            Ok(results)
+        } else {
+            Err(NativeError::DidNotConverge(results))
+        }
+    }
+}
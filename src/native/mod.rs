@@ -0,0 +1,148 @@
+//! Integration routines written in pure Rust, which need neither Cuba nor
+//! GSL to be installed.
+//!
+//! This module is gated with the `native` feature, which is on by default.
+//! To use only these routines, turn off the default features and turn on
+//! `native`:
+//!
+//! ```toml
+//! [dependencies]
+//! integrators = { version = "*", default-features = false, features = ["native"] }
+//! ```
+//!
+//! ```rust
+//! use integrators::{native, Integrator, Real};
+//!
+//! let res = native::GaussKronrod::new(1000)
+//!                                .with_range(0.0, ::std::f64::consts::PI)
+//!                                .integrate(|x: Real| x.sin(), 1e-10, 1e-12)
+//!                                .unwrap();
+//! assert!((res.results[0].value - 2.0).abs() < 1e-10);
+//! ```
+
+use std::{error, fmt, marker, vec};
+
+use super::traits::{IntegrandInput, IntegrandOutput, IntegrationResults};
+use super::{IntegrationResult, Real};
+
+#[cfg(test)]
+mod test;
+
+mod kronrod;
+
+mod gauss_kronrod;
+pub use self::gauss_kronrod::{GaussKronrod, GaussKronrodRule};
+
+mod tanh_sinh;
+pub use self::tanh_sinh::TanhSinh;
+
+mod rng;
+mod sequence;
+
+mod monte_carlo;
+pub use self::monte_carlo::PlainMonteCarlo;
+
+mod quasi_monte_carlo;
+pub use self::quasi_monte_carlo::{QuasiMonteCarlo, QMCSequence};
+
+mod vegas;
+pub use self::vegas::{Vegas, VegasGrid, VegasIntegrationResults, VegasIteration};
+
+mod cubature;
+pub use self::cubature::{Cubature, CUBATURE_MAX_DIM};
+
+/// An integrand, with the number of times it has been evaluated.
+struct Integrand<A, B, F> {
+    fun: F,
+    ncomp: usize,
+    neval: usize,
+    marker: marker::PhantomData<fn(A) -> B>,
+}
+
+impl<A, B, F> Integrand<A, B, F>
+    where A: IntegrandInput,
+          B: IntegrandOutput,
+          F: FnMut(A) -> B
+{
+    /// Wraps `fun`, evaluating it once at `probe` to find its number of
+    /// outputs. Returns `NativeError::BadComp` if it has none.
+    fn new(name: &'static str, mut fun: F, probe: &[Real]) -> Result<Self, NativeError> {
+        let ncomp = fun(A::from_args(probe)).output_size();
+        if ncomp == 0 {
+            return Err(NativeError::BadComp(name, ncomp));
+        }
+        Ok(Integrand {
+            fun, ncomp,
+            neval: 1,
+            marker: marker::PhantomData
+        })
+    }
+
+    /// Evaluates the integrand at `x`, writing its outputs to `out`.
+    fn call(&mut self, x: &[Real], out: &mut [Real]) {
+        self.neval += 1;
+        (self.fun)(A::from_args(x)).into_args(out)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NativeIntegrationResults {
+    /// The number of subregions used in the integration, for algorithms
+    /// which subdivide the integration region.
+    pub nregions: Option<usize>,
+    /// The number of evaluations used.
+    pub neval: usize,
+    /// Integration results, a vector of the same length as the integrand's
+    /// output dimensions.
+    pub results: Vec<IntegrationResult>,
+}
+
+impl IntegrationResults for NativeIntegrationResults {
+    type Iterator = vec::IntoIter<IntegrationResult>;
+    fn results(self) -> Self::Iterator {
+        self.results.into_iter()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NativeError {
+    /// The integrand input's dimensions are not supported by the given
+    /// algorithm. The name of the algorithm and the number of dimensions
+    /// attempted are given.
+    BadDim(&'static str, usize),
+    /// The integrand output's dimensions are not supported by the given
+    /// algorithm. The name of the algorithm and the number of dimensions
+    /// attempted are given.
+    BadComp(&'static str, usize),
+    /// A parameter of the integrator is invalid. The description says which,
+    /// and why.
+    InvalidParameter(&'static str),
+    /// The integration did not converge, because it ran out of subdivisions
+    /// or evaluations, or was limited by round-off error. Though the results
+    /// did not reach the desired uncertainty, they still might be useful,
+    /// and so are provided.
+    DidNotConverge(NativeIntegrationResults),
+    /// `Vegas` did not converge. Its results are provided with the
+    /// chi-squared of each iteration, which is the best guide to why.
+    VegasDidNotConverge(VegasIntegrationResults),
+}
+
+impl fmt::Display for NativeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        use self::NativeError::*;
+        match &self {
+            &BadDim(name, ndim) => {
+                write!(fmt, "invalid number of dimensions for algorithm {}: {}",
+                       name, ndim)
+            },
+            &BadComp(name, ncomp) => {
+                write!(fmt, "invalid number of outputs for algorithm {}: {}",
+                       name, ncomp)
+            },
+            &InvalidParameter(descr) => write!(fmt, "invalid parameter: {}", descr),
+            &DidNotConverge(_) | &VegasDidNotConverge(_) => write!(fmt, "integral did not converge")
+        }
+    }
+}
+
+impl error::Error for NativeError {}
@@ -0,0 +1,145 @@
+use std::f64;
+
+use ::{IntegrationResult, Integrator, Real};
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::rng::Rng;
+use super::{Integrand, NativeError, NativeIntegrationResults};
+
+/// Whether every component's error meets the tolerance.
+pub(super) fn meets_tolerance(results: &[IntegrationResult], epsrel: Real, epsabs: Real) -> bool {
+    results.iter()
+           .all(|res| res.error <= epsabs.max(epsrel * res.value.abs()))
+}
+
+/// Plain Monte Carlo integration over the unit hypercube, in pure Rust.
+/// Points are sampled uniformly at random, in batches of `nbatch`, until the
+/// error of every component of the integrand meets the requested tolerance,
+/// or `maxeval` points have been used.
+///
+/// The value is the mean of the samples, and the error is its standard
+/// error. Results are reproducible for a given seed.
+///
+/// ```
+/// use integrators::{native, Integrator, Real2};
+///
+/// let res = native::PlainMonteCarlo::new()
+///                                   .with_maxeval(1000000)
+///                                   .integrate(|(x, y): Real2| x * y, 1e-2, 1e-12)
+///                                   .unwrap();
+/// assert!((res.results[0].value - 0.25).abs() < 5.0 * res.results[0].error);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlainMonteCarlo {
+    mineval: usize,
+    maxeval: usize,
+    nbatch: usize,
+    seed: usize,
+}
+
+impl Default for PlainMonteCarlo {
+    fn default() -> Self {
+        PlainMonteCarlo {
+            mineval: 1000,
+            maxeval: 1000000,
+            nbatch: 1000,
+            seed: 0,
+        }
+    }
+}
+
+impl PlainMonteCarlo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum number of points to sample. (Default = 1000)
+    pub fn with_mineval(self, mineval: usize) -> Self {
+        PlainMonteCarlo { mineval, ..self }
+    }
+
+    /// Set the maximum number of points to sample. (Default = 1000000)
+    pub fn with_maxeval(self, maxeval: usize) -> Self {
+        PlainMonteCarlo { maxeval, ..self }
+    }
+
+    /// Set the number of points sampled between checks for convergence.
+    /// (Default = 1000)
+    pub fn with_nbatch(self, nbatch: usize) -> Self {
+        PlainMonteCarlo { nbatch, ..self }
+    }
+
+    /// Set the random number generator seed. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        PlainMonteCarlo { seed, ..self }
+    }
+}
+
+impl Integrator for PlainMonteCarlo {
+    type Success = NativeIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let ndim = A::input_size();
+        if ndim == 0 {
+            return Err(NativeError::BadDim("plain monte carlo", ndim));
+        }
+        if self.nbatch == 0 {
+            return Err(NativeError::InvalidParameter(
+                "plain monte carlo needs a batch of at least one point"));
+        }
+
+        let mut fun = Integrand::new("plain monte carlo", fun, &vec![0.5; ndim])?;
+        let ncomp = fun.ncomp;
+        let mut rng = Rng::new(self.seed as u64);
+        let mut x = vec![0.0; ndim];
+        let mut fx = vec![0.0; ncomp];
+
+        // Running means and sums of squared deviations (Welford's method)
+        let mut mean = vec![0.0; ncomp];
+        let mut m2 = vec![0.0; ncomp];
+        let mut npoints = 0;
+        loop {
+            let nbatch = self.nbatch.min(self.maxeval.saturating_sub(npoints)).max(1);
+            for _ in 0..nbatch {
+                for x in x.iter_mut() {
+                    *x = rng.next_real();
+                }
+                fun.call(&x, &mut fx);
+                npoints += 1;
+                for c in 0..ncomp {
+                    let delta = fx[c] - mean[c];
+                    mean[c] += delta / npoints as Real;
+                    m2[c] += delta * (fx[c] - mean[c]);
+                }
+            }
+
+            let results: Vec<IntegrationResult> = mean.iter().zip(m2.iter())
+                .map(|(&value, &m2)| IntegrationResult {
+                    value,
+                    error: if npoints > 1 {
+                        (m2 / ((npoints - 1) * npoints) as Real).sqrt()
+                    } else {
+                        f64::INFINITY
+                    },
+                })
+                .collect();
+            let converged = (npoints >= self.mineval)
+                && meets_tolerance(&results, epsrel, epsabs);
+            if converged || npoints >= self.maxeval {
+                let results = NativeIntegrationResults {
+                    nregions: None,
+                    neval: fun.neval,
+                    results,
+                };
+                return if converged {
+                    Ok(results)
+                } else {
+                    Err(NativeError::DidNotConverge(results))
+                };
+            }
+        }
+    }
+}
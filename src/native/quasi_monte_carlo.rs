@@ -0,0 +1,204 @@
+use ::{IntegrationResult, Integrator, Real};
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::monte_carlo::meets_tolerance;
+use super::rng::Rng;
+use super::sequence::{Halton, Sobol};
+use super::{Integrand, NativeError, NativeIntegrationResults};
+
+/// The low-discrepancy sequence sampled by `QuasiMonteCarlo`.
+#[derive(Debug, Hash, Copy, Clone, PartialEq, Eq)]
+pub enum QMCSequence {
+    /// The Sobol sequence. Best with a power of two number of points.
+    Sobol,
+    /// The Halton sequence, whose bases are the first primes. Its quality
+    /// degrades in more than about 10 dimensions.
+    Halton,
+}
+
+#[derive(Debug)]
+enum Sequence {
+    Sobol(Sobol),
+    Halton(Halton),
+}
+
+impl Sequence {
+    fn new(sequence: QMCSequence, ndim: usize) -> Self {
+        match sequence {
+            QMCSequence::Sobol => Sequence::Sobol(Sobol::new(ndim)),
+            QMCSequence::Halton => Sequence::Halton(Halton::new(ndim)),
+        }
+    }
+
+    fn next(&mut self, x: &mut [Real]) {
+        match *self {
+            Sequence::Sobol(ref mut seq) => seq.next(x),
+            Sequence::Halton(ref mut seq) => seq.next(x),
+        }
+    }
+}
+
+/// Randomized quasi-Monte Carlo integration over the unit hypercube, in
+/// pure Rust. Points of a low-discrepancy sequence cover the hypercube much
+/// more evenly than random points, so for smooth integrands the error
+/// shrinks nearly as `1/n`, rather than `1/sqrt(n)`.
+///
+/// The sequence is integrated `nshifts` times, each shifted (modulo 1) by a
+/// different random vector. Each shifted estimate is unbiased, and the
+/// value and error are the mean and standard error of the estimates. The
+/// number of points is doubled, starting from `nstart` per shift, until the
+/// error of every component of the integrand meets the requested tolerance,
+/// or another doubling would exceed `maxeval` evaluations.
+///
+/// ```
+/// use integrators::{native, Integrator, Real3};
+///
+/// let res = native::QuasiMonteCarlo::new()
+///                                   .with_sequence(native::QMCSequence::Halton)
+///                                   .integrate(|(x, y, z): Real3| x * y * z, 1e-4, 1e-12)
+///                                   .unwrap();
+/// assert!((res.results[0].value - 0.125).abs() < 1e-4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QuasiMonteCarlo {
+    sequence: QMCSequence,
+    nshifts: usize,
+    nstart: usize,
+    maxeval: usize,
+    seed: usize,
+}
+
+impl Default for QuasiMonteCarlo {
+    fn default() -> Self {
+        QuasiMonteCarlo {
+            sequence: QMCSequence::Sobol,
+            nshifts: 8,
+            nstart: 1024,
+            maxeval: 1 << 20,
+            seed: 0,
+        }
+    }
+}
+
+impl QuasiMonteCarlo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use a different low-discrepancy sequence.
+    /// (Default = `QMCSequence::Sobol`)
+    pub fn with_sequence(self, sequence: QMCSequence) -> Self {
+        QuasiMonteCarlo { sequence, ..self }
+    }
+
+    /// Set the number of random shifts the error is estimated from. At
+    /// least 2 are needed. (Default = 8)
+    pub fn with_nshifts(self, nshifts: usize) -> Self {
+        QuasiMonteCarlo { nshifts, ..self }
+    }
+
+    /// Set the number of points of the sequence first used for each shift.
+    /// `nstart * nshifts` must not exceed `maxeval`. (Default = 1024)
+    pub fn with_nstart(self, nstart: usize) -> Self {
+        QuasiMonteCarlo { nstart, ..self }
+    }
+
+    /// Set the maximum number of integrand evaluations, over all shifts.
+    /// (Default = 2^20)
+    pub fn with_maxeval(self, maxeval: usize) -> Self {
+        QuasiMonteCarlo { maxeval, ..self }
+    }
+
+    /// Set the seed of the random shifts. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        QuasiMonteCarlo { seed, ..self }
+    }
+
+    pub fn sequence(&self) -> QMCSequence {
+        self.sequence
+    }
+}
+
+impl Integrator for QuasiMonteCarlo {
+    type Success = NativeIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        let ndim = A::input_size();
+        if ndim == 0 {
+            return Err(NativeError::BadDim("quasi monte carlo", ndim));
+        }
+        if self.nshifts < 2 {
+            return Err(NativeError::InvalidParameter(
+                "quasi monte carlo needs at least 2 shifts to estimate the error"));
+        }
+        if self.nstart == 0 {
+            return Err(NativeError::InvalidParameter(
+                "quasi monte carlo needs to start with at least one point"));
+        }
+        if self.nstart * self.nshifts > self.maxeval {
+            return Err(NativeError::InvalidParameter(
+                "quasi monte carlo's first pass of nstart points per shift exceeds maxeval"));
+        }
+
+        let mut fun = Integrand::new("quasi monte carlo", fun, &vec![0.5; ndim])?;
+        let ncomp = fun.ncomp;
+        let mut rng = Rng::new(self.seed as u64);
+        let shifts: Vec<Vec<Real>> = (0..self.nshifts).map(|_| {
+            (0..ndim).map(|_| rng.next_real()).collect()
+        }).collect();
+        let mut sequence = Sequence::new(self.sequence, ndim);
+        let mut x = vec![0.0; ndim];
+        let mut shifted = vec![0.0; ndim];
+        let mut fx = vec![0.0; ncomp];
+
+        // The sum of the integrand over the points so far, for each shift
+        let mut sums = vec![vec![0.0; ncomp]; self.nshifts];
+        let mut npoints = 0;
+        let mut target = self.nstart;
+        loop {
+            while npoints < target {
+                sequence.next(&mut x);
+                for (shift, sums) in shifts.iter().zip(sums.iter_mut()) {
+                    for ((y, &x), &u) in shifted.iter_mut().zip(x.iter()).zip(shift.iter()) {
+                        *y = if x + u >= 1.0 { x + u - 1.0 } else { x + u };
+                    }
+                    fun.call(&shifted, &mut fx);
+                    for (sum, &f) in sums.iter_mut().zip(fx.iter()) {
+                        *sum += f;
+                    }
+                }
+                npoints += 1;
+            }
+
+            let nshifts = self.nshifts as Real;
+            let results: Vec<IntegrationResult> = (0..ncomp).map(|c| {
+                let estimates = sums.iter().map(|sums| sums[c] / npoints as Real);
+                let value = estimates.clone().sum::<Real>() / nshifts;
+                let var = estimates.map(|e| (e - value).powi(2)).sum::<Real>()
+                    / (nshifts - 1.0);
+                IntegrationResult {
+                    value,
+                    error: (var / nshifts).sqrt(),
+                }
+            }).collect();
+
+            let converged = meets_tolerance(&results, epsrel, epsabs);
+            if converged || 2 * npoints * self.nshifts > self.maxeval {
+                let results = NativeIntegrationResults {
+                    nregions: None,
+                    neval: fun.neval,
+                    results,
+                };
+                return if converged {
+                    Ok(results)
+                } else {
+                    Err(NativeError::DidNotConverge(results))
+                };
+            }
+            target *= 2;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use ::Real;
+
+/// A small, fast pseudo-random number generator (xoshiro256**), seeded with
+/// SplitMix64, so that integration results are reproducible from a seed.
+#[derive(Debug, Clone)]
+pub(super) struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub(super) fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let mut state = [0; 4];
+        for s in state.iter_mut() {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *s = z ^ (z >> 31);
+        }
+        Rng { state }
+    }
+
+    pub(super) fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// A uniform random number in [0, 1).
+    pub(super) fn next_real(&mut self) -> Real {
+        (self.next_u64() >> 11) as Real / (1u64 << 53) as Real
+    }
+}
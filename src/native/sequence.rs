@@ -0,0 +1,208 @@
+//! Low-discrepancy sequences for quasi-Monte Carlo integration.
+
+use ::Real;
+
+use super::rng::Rng;
+
+/// The number of bits of each coordinate of a Sobol point. The sequence
+/// has `2^SOBOL_BITS` points.
+const SOBOL_BITS: usize = 32;
+
+/// Seed for the initial direction numbers of the Sobol sequence.
+const SOBOL_SEED: u64 = 0x5EED_50B0;
+
+/// Multiplies two polynomials over GF(2), modulo `poly` of degree `deg`.
+fn mulmod(mut a: u64, mut b: u64, poly: u64, deg: u32) -> u64 {
+    let mut result = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            result ^= a;
+        }
+        b >>= 1;
+        a <<= 1;
+        if a & (1 << deg) != 0 {
+            a ^= poly;
+        }
+    }
+    result
+}
+
+/// `x^n` modulo `poly` of degree `deg`, over GF(2).
+fn powmod_x(mut n: u64, poly: u64, deg: u32) -> u64 {
+    // `x` itself, reduced if `poly` is linear
+    let mut base = if deg > 1 { 2 } else { 2 ^ poly };
+    let mut result = 1;
+    while n != 0 {
+        if n & 1 != 0 {
+            result = mulmod(result, base, poly, deg);
+        }
+        base = mulmod(base, base, poly, deg);
+        n >>= 1;
+    }
+    result
+}
+
+fn prime_factors(mut n: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p * p <= n {
+        if n.is_multiple_of(p) {
+            factors.push(p);
+            while n.is_multiple_of(p) {
+                n /= p;
+            }
+        }
+        p += 1;
+    }
+    if n > 1 {
+        factors.push(n);
+    }
+    factors
+}
+
+/// Whether `poly`, of degree `deg`, is primitive over GF(2), i.e. `x` has
+/// order `2^deg - 1` modulo `poly`.
+fn is_primitive(poly: u64, deg: u32) -> bool {
+    let order = (1u64 << deg) - 1;
+    if (poly & 1 == 0) | (powmod_x(order, poly, deg) != 1) {
+        return false;
+    }
+    prime_factors(order).into_iter()
+                        .all(|q| powmod_x(order / q, poly, deg) != 1)
+}
+
+/// The first `n` primitive polynomials over GF(2), in order of degree.
+fn primitive_polynomials(n: usize) -> Vec<(u64, u32)> {
+    let mut polys = Vec::with_capacity(n);
+    let mut deg = 1;
+    while polys.len() < n {
+        for poly in (1u64 << deg)..(1u64 << (deg + 1)) {
+            if polys.len() == n {
+                break;
+            }
+            if is_primitive(poly, deg) {
+                polys.push((poly, deg));
+            }
+        }
+        deg += 1;
+    }
+    polys
+}
+
+/// The Sobol sequence, generated in Gray code order.
+///
+/// The first dimension is the van der Corput sequence, and each further
+/// dimension uses the next primitive polynomial over GF(2). The initial
+/// direction numbers are chosen pseudo-randomly, with a fixed seed, as
+/// suggested by Bratley and Fox, rather than taken from a table of
+/// optimized values; randomly shifting the sequence, as `QuasiMonteCarlo`
+/// does, keeps the estimates unbiased either way.
+#[derive(Debug, Clone)]
+pub(super) struct Sobol {
+    /// `SOBOL_BITS` direction numbers for each dimension.
+    directions: Vec<[u32; SOBOL_BITS]>,
+    current: Vec<u32>,
+    index: u64,
+}
+
+impl Sobol {
+    pub(super) fn new(ndim: usize) -> Self {
+        let mut rng = Rng::new(SOBOL_SEED);
+        let mut directions = Vec::with_capacity(ndim);
+        if ndim > 0 {
+            let mut v = [0; SOBOL_BITS];
+            for (i, v) in v.iter_mut().enumerate() {
+                *v = 1 << (SOBOL_BITS - 1 - i);
+            }
+            directions.push(v);
+        }
+
+        for (poly, deg) in primitive_polynomials(ndim.saturating_sub(1)) {
+            let s = deg as usize;
+            let mut v = [0u32; SOBOL_BITS];
+            for (i, v) in v.iter_mut().enumerate().take(s) {
+                // An odd m_i < 2^(i + 1)
+                let m = ((rng.next_u64() % (1 << i)) << 1) | 1;
+                *v = (m as u32) << (SOBOL_BITS - 1 - i);
+            }
+            for i in s..SOBOL_BITS {
+                v[i] = v[i - s] ^ (v[i - s] >> s);
+                for k in 1..s {
+                    if (poly >> (s - k)) & 1 != 0 {
+                        v[i] ^= v[i - k];
+                    }
+                }
+            }
+            directions.push(v);
+        }
+
+        Sobol {
+            current: vec![0; ndim],
+            directions,
+            index: 0,
+        }
+    }
+
+    /// Writes the next point of the sequence to `x`.
+    ///
+    /// # Panics
+    /// If all `2^32` points of the sequence have been used.
+    pub(super) fn next(&mut self, x: &mut [Real]) {
+        for (x, &c) in x.iter_mut().zip(self.current.iter()) {
+            *x = c as Real / (1u64 << SOBOL_BITS) as Real;
+        }
+        let bit = (!self.index).trailing_zeros() as usize;
+        assert!(bit < SOBOL_BITS, "Sobol sequence exhausted");
+        for (c, v) in self.current.iter_mut().zip(self.directions.iter()) {
+            *c ^= v[bit];
+        }
+        self.index += 1;
+    }
+}
+
+/// The first `n` primes.
+fn primes(n: usize) -> Vec<u64> {
+    let mut primes: Vec<u64> = Vec::with_capacity(n);
+    let mut candidate = 2;
+    while primes.len() < n {
+        if primes.iter().take_while(|&&p| p * p <= candidate)
+                 .all(|&p| candidate % p != 0) {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+/// The Halton sequence, using the first `ndim` primes as bases.
+#[derive(Debug, Clone)]
+pub(super) struct Halton {
+    bases: Vec<u64>,
+    index: u64,
+}
+
+impl Halton {
+    pub(super) fn new(ndim: usize) -> Self {
+        Halton {
+            bases: primes(ndim),
+            index: 0,
+        }
+    }
+
+    /// Writes the next point of the sequence to `x`.
+    pub(super) fn next(&mut self, x: &mut [Real]) {
+        for (x, &base) in x.iter_mut().zip(self.bases.iter()) {
+            // The radical inverse of the index in this base
+            let mut n = self.index;
+            let mut scale = 1.0 / base as Real;
+            let mut value = 0.0;
+            while n != 0 {
+                value += (n % base) as Real * scale;
+                n /= base;
+                scale /= base as Real;
+            }
+            *x = value;
+        }
+        self.index += 1;
+    }
+}
@@ -0,0 +1,299 @@
+use std::{cmp, f64};
+use std::f64::consts::FRAC_PI_2;
+
+use ::{IntegrationResult, Integrator, Real, Real2};
+use ::traits::{IntegrandInput, IntegrandOutput};
+
+use super::{Integrand, NativeError, NativeIntegrationResults};
+
+/// Beyond this, every node of each transformation either lies on an
+/// endpoint (in floating point), or overflows.
+const MAX_T: Real = 7.0;
+
+/// Convergence is not checked before this level, as the first few levels
+/// may agree by coincidence.
+const MIN_LEVEL: usize = 4;
+
+/// The change of variables applied to the integration range.
+#[derive(Debug, Copy, Clone)]
+enum Transform {
+    /// Tanh-sinh, `x = tanh(pi/2 sinh(t))`, scaled to the finite range
+    /// [low, high].
+    Finite { low: Real, high: Real },
+    /// Exp-sinh, `x = origin + direction * exp(pi/2 sinh(t))`, for a range
+    /// which is infinite in one direction.
+    HalfInfinite { origin: Real, direction: Real },
+    /// Sinh-sinh, `x = sinh(pi/2 sinh(t))`, for the whole real line.
+    Infinite,
+}
+
+impl Transform {
+    /// The node for `t`, its signed distance from the nearer finite
+    /// endpoint, and its weight, or `None` if the node cannot be used
+    /// because it overflows, or lies on an endpoint. If `exact_endpoints`
+    /// is set, a node only lies on an endpoint if its distance is 0;
+    /// otherwise, it does if `x` is equal to the endpoint.
+    fn node(&self, t: Real, exact_endpoints: bool) -> Option<(Real, Real, Real)> {
+        let u = FRAC_PI_2 * t.sinh();
+        let (x, distance, w) = match *self {
+            Transform::Finite { low, high } => {
+                let half = 0.5 * (high - low);
+                // The distance of the node from the nearer end of [-1, 1],
+                // computed directly, so nodes close to the ends are
+                // resolved precisely.
+                let c = 2.0 / (1.0 + (2.0 * u.abs()).exp());
+                let (x, distance) = if t >= 0.0 {
+                    (high - half * c, -half * c)
+                } else {
+                    (low + half * c, half * c)
+                };
+                if exact_endpoints && distance == 0.0 {
+                    return None;
+                }
+                if !exact_endpoints && ((x == low) | (x == high)) {
+                    return None;
+                }
+                (x, distance, half * FRAC_PI_2 * t.cosh() / u.cosh().powi(2))
+            },
+            Transform::HalfInfinite { origin, direction } => {
+                let e = u.exp();
+                let x = origin + direction * e;
+                if (exact_endpoints && e == 0.0) || (!exact_endpoints && x == origin) {
+                    return None;
+                }
+                (x, direction * e, FRAC_PI_2 * t.cosh() * e)
+            },
+            Transform::Infinite => {
+                (u.sinh(), f64::INFINITY, FRAC_PI_2 * t.cosh() * u.cosh())
+            },
+        };
+        if x.is_finite() & w.is_finite() & (w != 0.0) {
+            Some((x, distance, w))
+        } else {
+            None
+        }
+    }
+}
+
+/// The nodes at which an integrand is evaluated.
+#[derive(Debug, Copy, Clone)]
+struct Nodes {
+    transform: Transform,
+    exact_endpoints: bool,
+}
+
+/// Adds the weighted integrand at the node for `t`, if there is one, to
+/// `sums`.
+fn add_node<B, F>(nodes: &Nodes, fun: &mut Integrand<Real2, B, F>, t: Real,
+                  fx: &mut [Real], sums: &mut [Real])
+    where B: IntegrandOutput,
+          F: FnMut(Real2) -> B
+{
+    if let Some((x, distance, w)) = nodes.transform.node(t, nodes.exact_endpoints) {
+        fun.call(&[x, distance], fx);
+        for (sum, &f) in sums.iter_mut().zip(fx.iter()) {
+            *sum += w * f;
+        }
+    }
+}
+
+/// Double exponential integration, in pure Rust. A change of variables
+/// makes the integrand decay double exponentially towards the ends of the
+/// range, where the trapezoid rule is then extremely accurate, even when
+/// the integrand has singularities at the endpoints.
+///
+/// Finite ranges use the tanh-sinh transformation. Ranges which are
+/// infinite in one direction use the exp-sinh transformation, and the whole
+/// real line uses sinh-sinh, so this is an alternative to `QAGIU`, `QAGIL`
+/// and `QAGI` for integrands which decay quickly enough.
+///
+/// Each level of refinement halves the step size of the trapezoid rule,
+/// reusing all previous evaluations, and the difference from the previous
+/// level is the error estimate. At least 4 levels are always used.
+///
+/// The integrand is never evaluated at either endpoint. Singularities at an
+/// endpoint other than 0 can only be resolved as finely as `x` near it can
+/// be represented, which limits the precision to about `1e-8` for a
+/// `1 / sqrt(x - a)` singularity; to do better, see
+/// `integrate_with_distance`.
+///
+/// ```
+/// use integrators::{native, Integrator, Real};
+///
+/// let mut ts = native::TanhSinh::new(10);
+/// let res = ts.integrate(|x: Real| x.ln(), 1e-12, 1e-14)
+///             .unwrap();
+/// assert!((res.results[0].value + 1.0).abs() < 1e-12);
+///
+/// let res = ts.with_range(0.0, ::std::f64::INFINITY)
+///             .integrate(|x: Real| (-x).exp(), 1e-12, 1e-14)
+///             .unwrap();
+/// assert!((res.results[0].value - 1.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TanhSinh {
+    range_low: Real,
+    range_high: Real,
+    max_levels: usize,
+}
+
+impl TanhSinh {
+    /// Creates a new `TanhSinh` which refines up to `max_levels` times,
+    /// using about `14 * 2^max_levels` evaluations at most. `max_levels`
+    /// must be at least 1, or integration fails with `InvalidParameter`.
+    /// This will integrate the range [0, 1]. To change the integration
+    /// bounds, see `with_range`.
+    pub fn new(max_levels: usize) -> Self {
+        TanhSinh {
+            range_low: 0.0,
+            range_high: 1.0,
+            max_levels
+        }
+    }
+
+    /// Set the maximum number of levels of refinement.
+    pub fn with_max_levels(self, max_levels: usize) -> Self {
+        TanhSinh { max_levels, ..self }
+    }
+
+    /// Use a different integration range. Either end may be infinite.
+    /// (Default = [0, 1])
+    pub fn with_range(self, range_low: Real, range_high: Real) -> Self {
+        TanhSinh { range_low, range_high, ..self }
+    }
+
+    pub fn range(&self) -> (Real, Real) {
+        (self.range_low, self.range_high)
+    }
+
+    /// Integrates `fun(x, distance)`, where `distance` is `x - e`, and `e`
+    /// is the endpoint of the range nearer to `x`. Unlike `x - e` computed
+    /// from `x`, `distance` is exact even when `x` is too close to `e` to be
+    /// distinguished from it, so singularities at `e` can be integrated to
+    /// full precision. Such nodes are used, so `fun` may be given `x` equal
+    /// to `e`, but never `distance` equal to 0. Ends of the range at
+    /// infinity are never the nearer endpoint; for the whole real line,
+    /// `distance` is always infinite.
+    ///
+    /// ```
+    /// use integrators::{native, Real};
+    ///
+    /// // 1 - x^2 = (1 - x) (1 + x), one of which is `distance`
+    /// let res = native::TanhSinh::new(12)
+    ///     .with_range(-1.0, 1.0)
+    ///     .integrate_with_distance(|x: Real, d: Real| {
+    ///         let (a, b) = if d < 0.0 { (-d, 1.0 + x) } else { (d, 1.0 - x) };
+    ///         (a * b).sqrt().recip()
+    ///     }, 1e-12, 1e-14)
+    ///     .unwrap();
+    /// assert!((res.results[0].value - ::std::f64::consts::PI).abs() < 1e-12);
+    /// ```
+    pub fn integrate_with_distance<B, F>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<NativeIntegrationResults, NativeError>
+        where B: IntegrandOutput,
+              F: FnMut(Real, Real) -> B
+    {
+        self.integrate_impl(true, |(x, distance): Real2| fun(x, distance), epsrel, epsabs)
+    }
+
+    fn integrate_impl<B, F>(&mut self, exact_endpoints: bool, fun: F, epsrel: Real, epsabs: Real) -> Result<NativeIntegrationResults, NativeError>
+        where B: IntegrandOutput,
+              F: FnMut(Real2) -> B
+    {
+        if self.max_levels == 0 {
+            return Err(NativeError::InvalidParameter(
+                "tanh-sinh needs at least one level of refinement"));
+        }
+        let (low, high, sign) = if self.range_low <= self.range_high {
+            (self.range_low, self.range_high, 1.0)
+        } else if self.range_low > self.range_high {
+            (self.range_high, self.range_low, -1.0)
+        } else {
+            return Err(NativeError::InvalidParameter(
+                "tanh-sinh range must not contain NaN"));
+        };
+        let transform = match (low.is_finite(), high.is_finite()) {
+            (true, true) => Transform::Finite { low, high },
+            (true, false) => Transform::HalfInfinite { origin: low, direction: 1.0 },
+            (false, true) => Transform::HalfInfinite { origin: high, direction: -1.0 },
+            (false, false) if low < high => Transform::Infinite,
+            (false, false) => return Err(NativeError::InvalidParameter(
+                "tanh-sinh cannot integrate from an infinity to itself")),
+        };
+
+        let nodes = Nodes { transform, exact_endpoints };
+
+        let probe = transform.node(0.0, exact_endpoints)
+                             .map(|(x, d, _)| [x, d])
+                             .unwrap_or([low, 0.0]);
+        let mut fun = Integrand::new("tanh-sinh", fun, &probe)?;
+        let ncomp = fun.ncomp;
+        let mut fx = vec![0.0; ncomp];
+
+        // The trapezoid sums, without the step size.
+        let mut sums = vec![0.0; ncomp];
+        let mut h = 1.0;
+        add_node(&nodes, &mut fun, 0.0, &mut fx, &mut sums);
+        let mut t = 1.0;
+        while t <= MAX_T {
+            add_node(&nodes, &mut fun, t, &mut fx, &mut sums);
+            add_node(&nodes, &mut fun, -t, &mut fx, &mut sums);
+            t += 1.0;
+        }
+
+        let mut value: Vec<Real> = sums.iter().map(|&s| h * s).collect();
+        let mut error = vec![f64::INFINITY; ncomp];
+        let mut converged = false;
+        for level in 1..self.max_levels + 1 {
+            h *= 0.5;
+            // Only the nodes halfway between the previous level's are new.
+            let mut j = 1.0;
+            while j * h <= MAX_T {
+                add_node(&nodes, &mut fun, j * h, &mut fx, &mut sums);
+                add_node(&nodes, &mut fun, -j * h, &mut fx, &mut sums);
+                j += 2.0;
+            }
+
+            for c in 0..ncomp {
+                let refined = h * sums[c];
+                error[c] = (refined - value[c]).abs();
+                value[c] = refined;
+            }
+            if level >= cmp::min(MIN_LEVEL, self.max_levels) {
+                converged = value.iter().zip(error.iter())
+                                 .all(|(&v, &e)| e <= epsabs.max(epsrel * v.abs()));
+                if converged {
+                    break;
+                }
+            }
+        }
+
+        let results = NativeIntegrationResults {
+            nregions: None,
+            neval: fun.neval,
+            results: value.into_iter().zip(error)
+                          .map(|(value, error)| IntegrationResult {
+                              value: sign * value, error
+                          })
+                          .collect(),
+        };
+        if converged {
+            Ok(results)
+        } else {
+            Err(NativeError::DidNotConverge(results))
+        }
+    }
+}
+
+impl Integrator for TanhSinh {
+    type Success = NativeIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, mut fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        if A::input_size() != 1 {
+            return Err(NativeError::BadDim("tanh-sinh", A::input_size()));
+        }
+        self.integrate_impl(false, |(x, _): Real2| fun(A::from_args(&[x])), epsrel, epsabs)
+    }
+}
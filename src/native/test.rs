@@ -0,0 +1,430 @@
+use std::f64;
+use std::f64::consts::PI;
+
+use ::{Integrator, Real, Real2, Real3, RealN};
+use super::{Cubature, GaussKronrod, GaussKronrodRule, NativeError, PlainMonteCarlo, QMCSequence,
+            QuasiMonteCarlo, TanhSinh, Vegas, VegasGrid};
+
+type OneDimCase = (Real, Real, fn(Real) -> Real, Real);
+
+const RULES: &[GaussKronrodRule] = &[GaussKronrodRule::G7K15,
+                                     GaussKronrodRule::G10K21,
+                                     GaussKronrodRule::G15K31,
+                                     GaussKronrodRule::G20K41,
+                                     GaussKronrodRule::G25K51,
+                                     GaussKronrodRule::G30K61];
+
+#[test]
+fn test_gauss_kronrod_polynomial() {
+    // A single application of each rule is exact for polynomials of degree
+    // up to 3n + 1.
+    for &rule in RULES.iter() {
+        let degree = 3 * (rule.npoints() - 1) / 2 + 1;
+        // The embedded Gauss rule is not exact, so this cannot converge
+        // without subdividing.
+        let res = match GaussKronrod::new(1)
+                                     .with_rule(rule)
+                                     .with_range(-1.0, 2.0)
+                                     .integrate(|x: Real| x.powi(degree as i32),
+                                                1e-10, 0.0) {
+            Ok(res) | Err(NativeError::DidNotConverge(res)) => res,
+            Err(err) => panic!("{:?}: {}", rule, err),
+        };
+        let exp = (2f64.powi(degree as i32 + 1) - (-1f64).powi(degree as i32 + 1))
+            / (degree + 1) as Real;
+        assert!((res.results[0].value - exp).abs() < 1e-12 * exp.abs(),
+                "{:?}: {} != {}", rule, res.results[0].value, exp);
+        assert_eq!(res.neval, rule.npoints() + 1);
+        assert_eq!(res.nregions, Some(1));
+    }
+}
+
+#[test]
+fn test_gauss_kronrod_adaptive() {
+    // A sharp peak at 0.3
+    let peak = |x: Real| 1.0 / ((x - 0.3).powi(2) + 1e-4);
+    let exp = 100.0 * (70f64.atan() + 30f64.atan());
+    for &rule in RULES.iter() {
+        let res = GaussKronrod::new(1000)
+                               .with_rule(rule)
+                               .integrate(peak, 1e-10, 0.0)
+                               .unwrap();
+        let res = res.results[0];
+        assert!((res.value - exp).abs() < 1e-9 * exp, "{:?}", rule);
+        assert!(res.error < 1e-10 * exp);
+    }
+
+    let res = GaussKronrod::new(1000)
+                           .with_range(0.0, PI)
+                           .integrate(|x: Real| (x.sin(), x.cos(), 1.0),
+                                      1e-10, 1e-12)
+                           .unwrap();
+    assert_eq!(res.results.len(), 3);
+    assert!((res.results[0].value - 2.0).abs() < 1e-10);
+    assert!(res.results[1].value.abs() < 1e-10);
+    assert!((res.results[2].value - PI).abs() < 1e-10);
+}
+
+#[test]
+fn test_gauss_kronrod_errors() {
+    let mut gk = GaussKronrod::new(1000);
+    assert_eq!(gk.integrate(|(x, y): Real2| x * y, 1e-6, 1e-6),
+               Err(NativeError::BadDim("gauss-kronrod", 2)));
+    assert_eq!(gk.integrate(|_: Real| Vec::<Real>::new(), 1e-6, 1e-6),
+               Err(NativeError::BadComp("gauss-kronrod", 0)));
+    match gk.integrate(|x: Real| x, 0.0, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+
+    // Integrable singularity, but too few subintervals
+    match gk.with_nintervals(3).integrate(|x: Real| x.sqrt().recip(), 1e-12, 0.0) {
+        Err(NativeError::DidNotConverge(res)) => {
+            assert_eq!(res.nregions, Some(3));
+            assert!((res.results[0].value - 2.0).abs() < 0.1);
+        },
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "gsl")]
+#[test]
+fn test_gauss_kronrod_matches_qag() {
+    use ::gsl::{QAG, QAGRule};
+
+    let qag_rules = [QAGRule::Gauss15, QAGRule::Gauss21, QAGRule::Gauss31,
+                     QAGRule::Gauss41, QAGRule::Gauss51, QAGRule::Gauss61];
+    let fun = |x: Real| (3.0 * x).cos() * (-x * x).exp();
+    for (&rule, &qag_rule) in RULES.iter().zip(qag_rules.iter()) {
+        let native = GaussKronrod::new(1000)
+                                  .with_rule(rule)
+                                  .with_range(-2.0, 5.0)
+                                  .integrate(fun, 1e-10, 1e-12)
+                                  .unwrap().results[0];
+        let gsl = QAG::new(1000)
+                      .with_rule(qag_rule)
+                      .with_range(-2.0, 5.0)
+                      .integrate(fun, 1e-10, 1e-12)
+                      .unwrap();
+        assert!((native.value - gsl.value).abs() <= gsl.error + native.error);
+    }
+}
+
+#[test]
+fn test_tanh_sinh_endpoint_singularities() {
+    let mut ts = TanhSinh::new(12);
+    let cases: &[OneDimCase] = &[
+        (0.0, 1.0, |x| x.sqrt().recip(), 2.0),
+        (0.0, 1.0, |x| x.ln(), -1.0),
+        (1.0, 0.0, |x| x.sqrt().recip(), -2.0),
+    ];
+    for &(low, high, fun, exp) in cases.iter() {
+        ts = ts.with_range(low, high);
+        let res = ts.integrate(fun, 1e-12, 1e-14).unwrap();
+        assert!((res.results[0].value - exp).abs() < 1e-11,
+                "[{}, {}]: {} != {}", low, high, res.results[0].value, exp);
+        assert!(res.results[0].error < 1e-11);
+    }
+}
+
+#[test]
+fn test_tanh_sinh_distance() {
+    let mut ts = TanhSinh::new(12).with_range(1.0, 2.0);
+    let res = ts.integrate_with_distance(|_: Real, d: Real| {
+        if d > 0.0 { d.sqrt().recip() } else { (1.0 + d).sqrt().recip() }
+    }, 1e-12, 1e-14).unwrap();
+    assert!((res.results[0].value - 2.0).abs() < 1e-12);
+
+    // (x - 1)^-1/2 (3 - x)^-1/2 over [1, 3] is pi
+    let res = ts.with_range(3.0, 1.0)
+                .integrate_with_distance(|x: Real, d: Real| {
+                    let (a, b) = if d > 0.0 { (d, 3.0 - x) } else { (x - 1.0, -d) };
+                    (a * b).sqrt().recip()
+                }, 1e-12, 1e-14)
+                .unwrap();
+    assert!((res.results[0].value + PI).abs() < 1e-12);
+}
+
+#[test]
+fn test_tanh_sinh_infinite_ranges() {
+    let mut ts = TanhSinh::new(12);
+    let cases: &[OneDimCase] = &[
+        (0.0, f64::INFINITY, |x| (-x).exp(), 1.0),
+        (1.0, f64::INFINITY, |x| x.powi(-2), 1.0),
+        (-f64::INFINITY, 0.0, |x| x.exp(), 1.0),
+        (-f64::INFINITY, f64::INFINITY, |x| (-x * x).exp(), PI.sqrt()),
+        (f64::INFINITY, -f64::INFINITY, |x| (1.0 + x * x).recip(), -PI),
+    ];
+    for &(low, high, fun, exp) in cases.iter() {
+        ts = ts.with_range(low, high);
+        let res = ts.integrate(fun, 1e-10, 1e-12).unwrap();
+        assert!((res.results[0].value - exp).abs() < 1e-9,
+                "[{}, {}]: {} != {}", low, high, res.results[0].value, exp);
+    }
+
+    let res = ts.with_range(0.0, f64::INFINITY)
+                .integrate(|x: Real| ((-x).exp(), (-2.0 * x).exp()), 1e-10, 1e-12)
+                .unwrap();
+    assert!((res.results[0].value - 1.0).abs() < 1e-10);
+    assert!((res.results[1].value - 0.5).abs() < 1e-10);
+}
+
+#[test]
+fn test_tanh_sinh_errors() {
+    let mut ts = TanhSinh::new(2);
+    match ts.integrate(|x: Real| (50.0 * x).sin(), 1e-14, 0.0) {
+        Err(NativeError::DidNotConverge(res)) => assert!(res.neval > 0),
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    assert_eq!(ts.integrate(|(x, y): Real2| x * y, 1e-6, 1e-6),
+               Err(NativeError::BadDim("tanh-sinh", 2)));
+    match ts.with_range(f64::INFINITY, f64::INFINITY).integrate(|x: Real| x, 1e-6, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+    assert_eq!(TanhSinh::new(0).integrate(|x: Real| x, 1e-6, 0.0),
+               Err(NativeError::InvalidParameter(
+                   "tanh-sinh needs at least one level of refinement")));
+}
+
+#[test]
+fn test_plain_monte_carlo() {
+    let mut mc = PlainMonteCarlo::new().with_seed(7);
+    let res = mc.integrate(|(x, y, z): Real3| (x * y * z, x + y + z), 1e-2, 1e-12)
+                .unwrap();
+    assert!((res.results[0].value - 0.125).abs() < 5.0 * res.results[0].error);
+    assert!((res.results[1].value - 1.5).abs() < 5.0 * res.results[1].error);
+    assert!(res.results[0].error <= 1e-2 * res.results[0].value.abs());
+    assert_eq!(res.nregions, None);
+
+    // The same seed gives the same results
+    assert_eq!(mc.integrate(|(x, y, z): Real3| (x * y * z, x + y + z), 1e-2, 1e-12),
+               Ok(res));
+}
+
+#[test]
+fn test_quasi_monte_carlo() {
+    for &sequence in [QMCSequence::Sobol, QMCSequence::Halton].iter() {
+        let res = QuasiMonteCarlo::new()
+                                 .with_sequence(sequence)
+                                 .integrate(|(x, y, z): Real3| {
+                                     (x * y * z, (x + y + z).exp())
+                                 }, 1e-4, 1e-12)
+                                 .unwrap();
+        let exps = [0.125, (f64::consts::E - 1.0).powi(3)];
+        for (res, &exp) in res.results.iter().zip(exps.iter()) {
+            assert!((res.value - exp).abs() < 5.0 * res.error, "{:?}: {} != {}",
+                    sequence, res.value, exp);
+            assert!(res.error <= 1e-4 * exp);
+        }
+        assert_eq!(res.nregions, None);
+    }
+
+    // Much more accurate than plain Monte Carlo, for the same evaluations
+    let mut qmc = QuasiMonteCarlo::new().with_seed(3);
+    let quasi = qmc.integrate(|(x, y): Real2| x * y, 1e-4, 0.0).unwrap();
+    let plain = PlainMonteCarlo::new()
+                                .with_mineval(quasi.neval)
+                                .with_maxeval(quasi.neval)
+                                .integrate(|(x, y): Real2| x * y, 0.0, 0.0);
+    match plain {
+        Err(NativeError::DidNotConverge(plain)) => {
+            assert!(quasi.results[0].error < plain.results[0].error)
+        },
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    assert_eq!(qmc.integrate(|(x, y): Real2| x * y, 1e-4, 0.0), Ok(quasi));
+}
+
+#[test]
+fn test_monte_carlo_errors() {
+    match PlainMonteCarlo::new().with_maxeval(100)
+                          .integrate(|(x, y): Real2| x * y, 1e-10, 0.0) {
+        Err(NativeError::DidNotConverge(res)) => assert_eq!(res.neval, 101),
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    match QuasiMonteCarlo::new().with_maxeval(10000)
+                          .integrate(|(x, y): Real2| x * y, 1e-14, 0.0) {
+        Err(NativeError::DidNotConverge(res)) => assert_eq!(res.neval, 8 * 1024 + 1),
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    assert_eq!(QuasiMonteCarlo::new().integrate(|_: Real| Vec::new(), 1e-6, 0.0),
+               Err(NativeError::BadComp("quasi monte carlo", 0)));
+    match QuasiMonteCarlo::new().with_nshifts(1).integrate(|x: Real| x, 1e-6, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+    // The first pass alone, of 8 shifts of 1024 points, would exceed maxeval
+    match QuasiMonteCarlo::new().with_maxeval(1000).integrate(|x: Real| x, 1e-6, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+}
+
+/// A narrow Gaussian peak at (0.3, 0.6), normalized to 1 over the plane.
+fn vegas_peak((x, y): Real2) -> Real {
+    let width: Real = 0.01;
+    (-((x - 0.3).powi(2) + (y - 0.6).powi(2)) / (2.0 * width.powi(2))).exp()
+        / (2.0 * PI * width.powi(2))
+}
+
+#[test]
+fn test_vegas_grid() {
+    let grid = VegasGrid::new(2, 4);
+    assert_eq!(grid.ndim(), 2);
+    assert_eq!(grid.ninc(), 4);
+    assert_eq!(grid.edges(1), &[0.0, 0.25, 0.5, 0.75, 1.0]);
+    assert_eq!(grid.to_string().parse::<VegasGrid>(), Ok(grid));
+
+    for edges in [vec![vec![0.0, 0.5, 1.0], vec![0.0, 1.0]],
+                  vec![vec![0.0, 0.5, 0.9]],
+                  vec![vec![0.0, 0.5, 0.5, 1.0]],
+                  vec![vec![0.0]]].iter() {
+        match VegasGrid::from_edges(edges.clone()) {
+            Err(NativeError::InvalidParameter(_)) => (),
+            other => panic!("expected invalid parameter, got {:?}", other),
+        }
+    }
+    for text in &["", "vegas grid 1 2", "vegas grid 1 2\n0 0.5", "grid 1 1\n0 1"] {
+        assert!(text.parse::<VegasGrid>().is_err(), "{:?}", text);
+    }
+}
+
+#[test]
+fn test_vegas_training() {
+    let mut vegas = Vegas::new().with_seed(3);
+    let training = vegas.train(vegas_peak, 10).unwrap();
+    assert_eq!(training.iterations.len(), 10);
+    assert_eq!(training.neval,
+               1 + training.iterations.iter().map(|it| it.neval).sum::<usize>());
+
+    // The increments around the peak are much narrower than those away
+    // from it.
+    let grid = vegas.grid().unwrap().clone();
+    for &(dim, peak) in [(0, 0.3), (1, 0.6)].iter() {
+        let edges = grid.edges(dim);
+        let widths: Vec<(Real, Real)> = edges.windows(2)
+                                             .map(|w| (0.5 * (w[0] + w[1]), w[1] - w[0]))
+                                             .collect();
+        let near = widths.iter().find(|&&(mid, _)| (mid - peak).abs() < 0.01).unwrap().1;
+        let far = widths.iter().find(|&&(mid, _)| (mid - peak).abs() > 0.2).unwrap().1;
+        assert!(far > 10.0 * near, "{}: {} vs {}", dim, far, near);
+    }
+
+    // A frozen grid is not adapted.
+    let mut vegas = vegas.with_frozen(true);
+    let res = vegas.integrate(vegas_peak, 1e-3, 0.0).unwrap();
+    assert_eq!(vegas.grid(), Some(&grid));
+    assert!((res.results[0].value - 1.0).abs() < 5.0 * res.results[0].error);
+    assert!(res.results[0].error <= 1e-3);
+    assert_eq!(res.chi2_dof, res.iterations.last().unwrap().chi2_dof);
+    assert_eq!(res.iterations[0].chi2_dof, vec![0.0]);
+    assert!(res.chi2_dof[0] < 5.0);
+
+    // The trained grid can be reused through its text form, and is far
+    // better than a uniform one.
+    let text = grid.to_string();
+    let mut trained = Vegas::new().with_grid(text.parse().unwrap()).with_frozen(true);
+    let mut uniform = Vegas::new().with_frozen(true);
+    let trained = trained.integrate(vegas_peak, 1e-3, 0.0).unwrap();
+    let uniform = match uniform.integrate(vegas_peak, 1e-3, 0.0) {
+        Ok(res) => res.neval,
+        Err(NativeError::VegasDidNotConverge(res)) => res.neval,
+        Err(err) => panic!("{}", err),
+    };
+    assert!(10 * trained.neval < uniform, "{} vs {}", trained.neval, uniform);
+}
+
+#[test]
+fn test_vegas() {
+    let res = Vegas::new().integrate(|(x, y, z): Real3| (x * y * z, (x + y + z).exp()),
+                                     1e-3, 1e-12)
+                          .unwrap();
+    let exps = [0.125, (f64::consts::E - 1.0).powi(3)];
+    for (res, &exp) in res.results.iter().zip(exps.iter()) {
+        assert!((res.value - exp).abs() < 5.0 * res.error);
+        assert!(res.error <= 1e-3 * exp);
+    }
+    assert_eq!(res.chi2_dof.len(), 2);
+
+    match Vegas::new().with_maxeval(2000).integrate(vegas_peak, 1e-6, 0.0) {
+        Err(NativeError::VegasDidNotConverge(res)) => {
+            assert_eq!(res.neval, 1001);
+            // The diagnostics of each iteration are kept
+            assert_eq!(res.iterations.len(), 1);
+            assert_eq!(res.chi2_dof, res.iterations[0].chi2_dof);
+        },
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    let mut vegas = Vegas::new().with_grid(VegasGrid::new(3, 10));
+    match vegas.integrate(vegas_peak, 1e-3, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+    assert_eq!(vegas.grid(), Some(&VegasGrid::new(3, 10)));
+    match vegas.with_frozen(true).train(vegas_peak, 5) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cubature() {
+    // In 1 dimension, as well as more
+    let res = Cubature::new(100000).integrate(|x: Real| (x * PI).sin(), 1e-12, 0.0)
+                                   .unwrap();
+    assert!((res.results[0].value - 2.0 / PI).abs() < 1e-12);
+
+    // Both rules are exact for polynomials of degree 5, so these converge
+    // without subdividing.
+    let res = Cubature::new(100000)
+                       .integrate(|(x, y, z): Real3| x.powi(3) * y.powi(2) + z.powi(5),
+                                  1e-12, 0.0)
+                       .unwrap();
+    assert!((res.results[0].value - 0.25).abs() < 1e-14);
+    assert_eq!(res.nregions, Some(1));
+
+    // Vector outputs, with a sharp peak
+    let res = Cubature::new(1000000)
+                       .integrate(|(x, y): Real2| {
+                           vec![1.0 / ((x - 0.3).powi(2) + (y - 0.7).powi(2) + 1e-2),
+                                x * y]
+                       }, 1e-8, 1e-12)
+                       .unwrap();
+    // Compare with nested 1 dimensional integrals
+    let exp = GaussKronrod::new(1000).integrate(|x: Real| {
+        GaussKronrod::new(1000)
+                     .integrate(|y: Real| 1.0 / ((x - 0.3).powi(2) + (y - 0.7).powi(2) + 1e-2),
+                                1e-13, 0.0)
+                     .unwrap().results[0].value
+    }, 1e-12, 0.0).unwrap().results[0].value;
+    assert!((res.results[0].value - exp).abs() < 1e-8 * exp);
+    assert!((res.results[1].value - 0.25).abs() < 1e-12);
+    assert!(res.nregions.unwrap() > 1);
+
+    match Cubature::new(1000).integrate(|(x, y): Real2| (x - y).abs().sqrt(), 1e-12, 0.0) {
+        Err(NativeError::DidNotConverge(res)) => assert!(res.neval <= 1000),
+        other => panic!("expected non-convergence, got {:?}", other),
+    }
+    match Cubature::new(10).integrate(|(x, y): Real2| x * y, 1e-6, 0.0) {
+        Err(NativeError::InvalidParameter(_)) => (),
+        other => panic!("expected invalid parameter, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_array_integrands() {
+    // Many dimensions, with array inputs and outputs
+    let res = QuasiMonteCarlo::new()
+                              .integrate(|x: RealN<10>| [x.iter().sum::<Real>(), x[0] * x[9]],
+                                         1e-4, 1e-10)
+                              .unwrap();
+    assert_eq!(res.results.len(), 2);
+    assert!((res.results[0].value - 5.0).abs() < 1e-3, "{:?}", res);
+    assert!((res.results[1].value - 0.25).abs() < 1e-3, "{:?}", res);
+
+    let res = Cubature::new(100000)
+                       .integrate(|x: RealN<12>| x.iter().product::<Real>(), 1e-8, 0.0)
+                       .unwrap();
+    assert!((res.results[0].value - 0.5f64.powi(12)).abs() < 1e-10, "{:?}", res);
+}
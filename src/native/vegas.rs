@@ -0,0 +1,605 @@
+use std::{f64, fmt, str};
+
+use ::{IntegrationResult, Integrator, Real};
+use ::traits::{IntegrandInput, IntegrandOutput, IntegrationResults};
+
+use super::monte_carlo::meets_tolerance;
+use super::rng::Rng;
+use super::{Integrand, NativeError};
+
+/// The importance sampling grid of `Vegas`. Each axis of the unit hypercube
+/// is divided into `ninc` increments, each of which is sampled with equal
+/// probability, so narrow increments are sampled more densely than wide
+/// ones. Adapting the grid narrows the increments where the integrand is
+/// largest.
+///
+/// A grid can be written as text with `Display`, and read back with
+/// `FromStr`, exactly:
+///
+/// ```
+/// use integrators::native::VegasGrid;
+///
+/// let grid = VegasGrid::from_edges(vec![vec![0.0, 0.1, 1.0],
+///                                       vec![0.0, 0.7, 1.0]]).unwrap();
+/// let text = grid.to_string();
+/// assert_eq!(text.parse::<VegasGrid>(), Ok(grid));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct VegasGrid {
+    ndim: usize,
+    ninc: usize,
+    /// The `ninc + 1` edges of the increments of each axis, one axis after
+    /// another.
+    edges: Vec<Real>,
+}
+
+impl VegasGrid {
+    /// Creates a uniform grid of `ndim` axes, each with `ninc` increments.
+    ///
+    /// # Panics
+    /// If `ninc` is 0.
+    pub fn new(ndim: usize, ninc: usize) -> Self {
+        assert!(ninc > 0, "a vegas grid needs at least one increment");
+        let axis: Vec<Real> = (0..ninc + 1).map(|i| i as Real / ninc as Real)
+                                           .collect();
+        VegasGrid {
+            ndim, ninc,
+            edges: axis.iter().cloned().cycle().take(ndim * (ninc + 1)).collect(),
+        }
+    }
+
+    /// Creates a grid from the edges of the increments of each axis. Every
+    /// axis must have the same number of edges, at least 2, increasing from
+    /// 0 to 1.
+    pub fn from_edges(axes: Vec<Vec<Real>>) -> Result<Self, NativeError> {
+        let ndim = axes.len();
+        let nedges = axes.first().map_or(2, |axis| axis.len());
+        if nedges < 2 {
+            return Err(NativeError::InvalidParameter(
+                "vegas grid axes need at least 2 edges"));
+        }
+        let mut edges = Vec::with_capacity(ndim * nedges);
+        for axis in axes {
+            if axis.len() != nedges {
+                return Err(NativeError::InvalidParameter(
+                    "vegas grid axes must have the same number of edges"));
+            }
+            if (axis[0] != 0.0) | (axis[nedges - 1] != 1.0)
+                || !axis.windows(2).all(|w| w[0] < w[1]) {
+                return Err(NativeError::InvalidParameter(
+                    "vegas grid edges must increase from 0 to 1"));
+            }
+            edges.extend(axis);
+        }
+        Ok(VegasGrid {
+            ndim,
+            ninc: nedges - 1,
+            edges,
+        })
+    }
+
+    /// The number of axes.
+    pub fn ndim(&self) -> usize {
+        self.ndim
+    }
+
+    /// The number of increments of each axis.
+    pub fn ninc(&self) -> usize {
+        self.ninc
+    }
+
+    /// The `ninc + 1` edges of the increments of axis `dim`, from 0 to 1.
+    ///
+    /// # Panics
+    /// If `dim` is not less than `ndim`.
+    pub fn edges(&self, dim: usize) -> &[Real] {
+        assert!(dim < self.ndim, "no axis {} in a {} dimensional vegas grid",
+                dim, self.ndim);
+        let nedges = self.ninc + 1;
+        &self.edges[dim * nedges..(dim + 1) * nedges]
+    }
+
+    /// Maps `y`, uniform in the unit hypercube, to `x`, distributed
+    /// according to the grid, writing the increment of each axis to `inc`.
+    /// Returns the Jacobian of the map.
+    fn map(&self, y: &[Real], x: &mut [Real], inc: &mut [usize]) -> Real {
+        let ninc = self.ninc as Real;
+        let mut jac = 1.0;
+        for (dim, ((&y, x), inc)) in y.iter().zip(x.iter_mut()).zip(inc.iter_mut()).enumerate() {
+            let edges = self.edges(dim);
+            let pos = y * ninc;
+            let i = (pos as usize).min(self.ninc - 1);
+            let width = edges[i + 1] - edges[i];
+            *x = edges[i] + (pos - i as Real) * width;
+            *inc = i;
+            jac *= ninc * width;
+        }
+        jac
+    }
+
+    /// Moves the edges of each axis so that each increment would have
+    /// contributed equally to the variance, according to the sum of the
+    /// squared integrand in each increment, `weights`. `alpha` damps the
+    /// change.
+    fn refine(&mut self, weights: &[Real], alpha: Real) {
+        let ninc = self.ninc;
+        let mut new_edges = vec![0.0; ninc + 1];
+        for dim in 0..self.ndim {
+            let d = &weights[dim * ninc..(dim + 1) * ninc];
+            // Smooth the weights with their neighbours, then compress their
+            // range, so the grid does not change too abruptly.
+            let smoothed: Vec<Real> = (0..ninc).map(|i| {
+                let low = if i > 0 { i - 1 } else { 0 };
+                let high = (i + 1).min(ninc - 1);
+                d[low..high + 1].iter().sum::<Real>() / (high + 1 - low) as Real
+            }).collect();
+            let total: Real = smoothed.iter().sum();
+            if (total <= 0.0) || !total.is_finite() {
+                continue;
+            }
+            let rates: Vec<Real> = smoothed.iter().map(|&d| {
+                let d = d / total;
+                if d <= 0.0 {
+                    0.0
+                } else if d >= 1.0 {
+                    1.0
+                } else {
+                    ((1.0 - d) / -d.ln()).powf(alpha)
+                }
+            }).collect();
+            let step = rates.iter().sum::<Real>() / ninc as Real;
+
+            let edges = &mut self.edges[dim * (ninc + 1)..(dim + 1) * (ninc + 1)];
+            let mut acc = 0.0;
+            let mut i = 0;
+            for (k, new_edge) in new_edges.iter_mut().enumerate().take(ninc).skip(1) {
+                let target = k as Real * step;
+                while (i < ninc - 1) && (acc + rates[i] < target) {
+                    acc += rates[i];
+                    i += 1;
+                }
+                let frac = if rates[i] > 0.0 {
+                    ((target - acc) / rates[i]).min(1.0)
+                } else {
+                    0.0
+                };
+                *new_edge = edges[i] + frac * (edges[i + 1] - edges[i]);
+            }
+            new_edges[ninc] = 1.0;
+            // Keep the edges strictly increasing, even after round-off.
+            if new_edges.windows(2).all(|w| w[0] < w[1]) {
+                edges.copy_from_slice(&new_edges);
+            }
+        }
+    }
+}
+
+impl fmt::Display for VegasGrid {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "vegas grid {} {}", self.ndim, self.ninc)?;
+        for dim in 0..self.ndim {
+            let edges: Vec<String> = self.edges(dim).iter()
+                                         .map(|e| e.to_string())
+                                         .collect();
+            writeln!(fmt, "{}", edges.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl str::FromStr for VegasGrid {
+    type Err = NativeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const MALFORMED: NativeError = NativeError::InvalidParameter("malformed vegas grid");
+        let mut lines = s.lines();
+        let header: Vec<&str> = lines.next().ok_or(MALFORMED)?
+                                     .split_whitespace().collect();
+        if (header.len() != 4) || (header[0] != "vegas") || (header[1] != "grid") {
+            return Err(MALFORMED);
+        }
+        let ndim = header[2].parse::<usize>().map_err(|_| MALFORMED)?;
+        let ninc = header[3].parse::<usize>().map_err(|_| MALFORMED)?;
+        let axes = lines.filter(|line| !line.trim().is_empty())
+                        .map(|line| {
+                            line.split_whitespace()
+                                .map(|e| e.parse::<Real>().map_err(|_| MALFORMED))
+                                .collect::<Result<Vec<Real>, _>>()
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+        if axes.len() != ndim {
+            return Err(MALFORMED);
+        }
+        let grid = VegasGrid::from_edges(axes)?;
+        if ndim > 0 && grid.ninc != ninc {
+            return Err(MALFORMED);
+        }
+        Ok(VegasGrid { ninc, ..grid })
+    }
+}
+
+/// The results of a single iteration of `Vegas`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VegasIteration {
+    /// The number of evaluations used in this iteration.
+    pub neval: usize,
+    /// The estimates of this iteration alone, one per component of the
+    /// integrand.
+    pub results: Vec<IntegrationResult>,
+    /// The chi-squared per degree of freedom of the estimates of every
+    /// iteration so far, one per component. This should be near 1 if they
+    /// are consistent; much larger values indicate the error estimates are
+    /// unreliable. It is 0 after the first iteration.
+    pub chi2_dof: Vec<Real>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VegasIntegrationResults {
+    /// The number of evaluations used.
+    pub neval: usize,
+    /// The estimates of every iteration, combined, weighted by their
+    /// inverse variance. A vector of the same length as the integrand's
+    /// output dimensions.
+    pub results: Vec<IntegrationResult>,
+    /// The chi-squared per degree of freedom of the combined estimates, one
+    /// per component.
+    pub chi2_dof: Vec<Real>,
+    /// The results of each iteration, in order.
+    pub iterations: Vec<VegasIteration>,
+}
+
+impl IntegrationResults for VegasIntegrationResults {
+    type Iterator = ::std::vec::IntoIter<IntegrationResult>;
+    fn results(self) -> Self::Iterator {
+        self.results.into_iter()
+    }
+}
+
+/// The running inverse-variance weighted combination of the estimates of
+/// each iteration.
+struct Combination {
+    /// Sums of the weights, the weighted estimates, and the weighted squared
+    /// estimates, for each component.
+    weights: Vec<Real>,
+    values: Vec<Real>,
+    squares: Vec<Real>,
+    niter: usize,
+}
+
+impl Combination {
+    fn new(ncomp: usize) -> Self {
+        Combination {
+            weights: vec![0.0; ncomp],
+            values: vec![0.0; ncomp],
+            squares: vec![0.0; ncomp],
+            niter: 0,
+        }
+    }
+
+    fn add(&mut self, results: &[IntegrationResult]) {
+        for (c, res) in results.iter().enumerate() {
+            // An exact estimate would have an infinite weight.
+            let var = res.error.powi(2)
+                               .max((f64::EPSILON * res.value).powi(2))
+                               .max(f64::MIN_POSITIVE);
+            self.weights[c] += 1.0 / var;
+            self.values[c] += res.value / var;
+            self.squares[c] += res.value.powi(2) / var;
+        }
+        self.niter += 1;
+    }
+
+    fn results(&self) -> Vec<IntegrationResult> {
+        self.weights.iter().zip(self.values.iter())
+            .map(|(&w, &v)| IntegrationResult {
+                value: v / w,
+                error: w.recip().sqrt(),
+            })
+            .collect()
+    }
+
+    fn chi2_dof(&self) -> Vec<Real> {
+        if self.niter < 2 {
+            return vec![0.0; self.weights.len()];
+        }
+        self.weights.iter().zip(self.values.iter()).zip(self.squares.iter())
+            .map(|((&w, &v), &s)| (s - v * v / w).max(0.0) / (self.niter - 1) as Real)
+            .collect()
+    }
+}
+
+/// The VEGAS algorithm of Lepage, in pure Rust: adaptive importance
+/// sampling Monte Carlo integration over the unit hypercube.
+///
+/// Each iteration samples the integrand at random points distributed
+/// according to a `VegasGrid`, then adapts the grid to concentrate points
+/// where the integrand is largest in magnitude, as measured by the sum of
+/// the squares of all of its components. The estimates of all iterations
+/// are combined, and iterations continue until the combined error of every
+/// component meets the requested tolerance, or `maxeval` would be exceeded.
+/// In that case, the integration fails with
+/// `NativeError::VegasDidNotConverge`, which keeps the chi-squared of every
+/// iteration.
+///
+/// Unlike Cuba's `Vegas`, the grid is kept in the integrator, where it can
+/// be inspected with `grid`, and reused by later integrations of the same
+/// number of dimensions. A grid can be trained on an integrand with
+/// `train`, and frozen with `with_frozen`, so that it is not adapted
+/// further; the estimates of a frozen grid are statistically independent,
+/// so their chi-squared is more trustworthy.
+///
+/// ```
+/// use integrators::{native, Integrator, Real2};
+///
+/// // A narrow peak at (0.5, 0.5)
+/// let peak = |(x, y): Real2| {
+///     (-((x - 0.5).powi(2) + (y - 0.5).powi(2)) / 0.002).exp() / (0.002 * ::std::f64::consts::PI)
+/// };
+/// let mut vegas = native::Vegas::new().with_seed(1);
+/// vegas.train(peak, 10).unwrap();
+/// let res = vegas.with_frozen(true)
+///                .integrate(peak, 1e-3, 1e-12)
+///                .unwrap();
+/// assert!((res.results[0].value - 1.0).abs() < 5.0 * res.results[0].error);
+/// assert!(res.chi2_dof[0] < 5.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Vegas {
+    mineval: usize,
+    maxeval: usize,
+    nstart: usize,
+    nincrease: usize,
+    ninc: usize,
+    alpha: Real,
+    seed: usize,
+    frozen: bool,
+    grid: Option<VegasGrid>,
+    rng: Option<Rng>,
+}
+
+impl Default for Vegas {
+    fn default() -> Self {
+        Vegas {
+            mineval: 0,
+            maxeval: 1000000,
+            nstart: 1000,
+            nincrease: 500,
+            ninc: 128,
+            alpha: 1.5,
+            seed: 0,
+            frozen: false,
+            grid: None,
+            rng: None,
+        }
+    }
+}
+
+impl Vegas {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum number of evaluations. (Default = 0)
+    pub fn with_mineval(self, mineval: usize) -> Self {
+        Vegas { mineval, ..self }
+    }
+
+    /// Set the maximum number of evaluations. (Default = 1000000)
+    pub fn with_maxeval(self, maxeval: usize) -> Self {
+        Vegas { maxeval, ..self }
+    }
+
+    /// Set the number of evaluations of the first iteration.
+    /// (Default = 1000)
+    pub fn with_nstart(self, nstart: usize) -> Self {
+        Vegas { nstart, ..self }
+    }
+
+    /// Set the increase in the number of evaluations of each iteration over
+    /// the last. (Default = 500)
+    pub fn with_nincrease(self, nincrease: usize) -> Self {
+        Vegas { nincrease, ..self }
+    }
+
+    /// Set the number of increments of each axis of new grids. This does
+    /// not affect a grid which already exists. (Default = 128)
+    pub fn with_ninc(self, ninc: usize) -> Self {
+        Vegas { ninc, ..self }
+    }
+
+    /// Set how quickly the grid adapts. Larger values adapt faster, and 0
+    /// does not adapt at all. (Default = 1.5)
+    pub fn with_alpha(self, alpha: Real) -> Self {
+        Vegas { alpha, ..self }
+    }
+
+    /// Set the random number generator seed. Later integrations continue
+    /// from where the random numbers of the last one stopped, until the
+    /// seed is set again. (Default = 0)
+    pub fn with_seed(self, seed: usize) -> Self {
+        Vegas { seed, rng: None, ..self }
+    }
+
+    /// Stop adapting the grid. (Default = false)
+    pub fn with_frozen(self, frozen: bool) -> Self {
+        Vegas { frozen, ..self }
+    }
+
+    /// Use `grid`, for instance one trained on another integrand, or read
+    /// from a file.
+    pub fn with_grid(self, grid: VegasGrid) -> Self {
+        Vegas { grid: Some(grid), ..self }
+    }
+
+    /// Discard the grid, so the next integration starts from a uniform one.
+    pub fn without_grid(self) -> Self {
+        Vegas { grid: None, ..self }
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// The grid, once one has been given, or made by an integration.
+    pub fn grid(&self) -> Option<&VegasGrid> {
+        self.grid.as_ref()
+    }
+
+    /// Adapts the grid to `fun` over `niter` iterations, regardless of
+    /// `maxeval`, and returns the estimates made along the way. The grid is
+    /// kept for the following integrations.
+    pub fn train<A, B, F>(&mut self, fun: F, niter: usize) -> Result<VegasIntegrationResults, NativeError>
+        where A: IntegrandInput,
+              B: IntegrandOutput,
+              F: FnMut(A) -> B
+    {
+        if self.frozen {
+            return Err(NativeError::InvalidParameter(
+                "vegas cannot train a frozen grid"));
+        }
+        if niter == 0 {
+            return Err(NativeError::InvalidParameter(
+                "vegas needs at least one training iteration"));
+        }
+        self.run(fun, Some(niter), 0.0, 0.0)
+    }
+
+    /// Runs `niter` iterations, if given, or else until the tolerance is
+    /// met.
+    fn run<A, B, F>(&mut self, fun: F, niter: Option<usize>, epsrel: Real, epsabs: Real) -> Result<VegasIntegrationResults, NativeError>
+        where A: IntegrandInput,
+              B: IntegrandOutput,
+              F: FnMut(A) -> B
+    {
+        let ndim = A::input_size();
+        if ndim == 0 {
+            return Err(NativeError::BadDim("vegas", ndim));
+        }
+        if self.nstart < 2 {
+            return Err(NativeError::InvalidParameter(
+                "vegas needs at least 2 evaluations per iteration"));
+        }
+        let mut grid = match self.grid.take() {
+            Some(grid) => {
+                if grid.ndim != ndim {
+                    self.grid = Some(grid);
+                    return Err(NativeError::InvalidParameter(
+                        "vegas grid has a different number of dimensions than the integrand"));
+                }
+                grid
+            },
+            None if self.ninc == 0 => {
+                return Err(NativeError::InvalidParameter(
+                    "vegas grids need at least one increment"));
+            },
+            None => VegasGrid::new(ndim, self.ninc),
+        };
+
+        let mut fun = match Integrand::new("vegas", fun, &vec![0.5; ndim]) {
+            Ok(fun) => fun,
+            Err(err) => {
+                self.grid = Some(grid);
+                return Err(err);
+            },
+        };
+        let ncomp = fun.ncomp;
+        let seed = self.seed as u64;
+        let mut rng = self.rng.take().unwrap_or_else(|| Rng::new(seed));
+        let mut y = vec![0.0; ndim];
+        let mut x = vec![0.0; ndim];
+        let mut inc = vec![0; ndim];
+        let mut fx = vec![0.0; ncomp];
+        let mut sums = vec![0.0; ncomp];
+        let mut squares = vec![0.0; ncomp];
+        let mut weights = vec![0.0; ndim * grid.ninc];
+
+        let mut combination = Combination::new(ncomp);
+        let mut iterations = Vec::new();
+        let mut npoints = 0;
+        let converged = loop {
+            let n = self.nstart + iterations.len() * self.nincrease;
+            if niter.map_or(npoints + n > self.maxeval, |niter| iterations.len() == niter) {
+                break niter.is_some();
+            }
+
+            for s in sums.iter_mut().chain(squares.iter_mut()).chain(weights.iter_mut()) {
+                *s = 0.0;
+            }
+            for _ in 0..n {
+                for y in y.iter_mut() {
+                    *y = rng.next_real();
+                }
+                let jac = grid.map(&y, &mut x, &mut inc);
+                fun.call(&x, &mut fx);
+                let mut f2 = 0.0;
+                for c in 0..ncomp {
+                    let f = fx[c] * jac;
+                    sums[c] += f;
+                    squares[c] += f * f;
+                    f2 += f * f;
+                }
+                for (dim, &i) in inc.iter().enumerate() {
+                    weights[dim * grid.ninc + i] += f2;
+                }
+            }
+            npoints += n;
+
+            let nreal = n as Real;
+            let results: Vec<IntegrationResult> = sums.iter().zip(squares.iter())
+                .map(|(&sum, &square)| {
+                    let mean = sum / nreal;
+                    IntegrationResult {
+                        value: mean,
+                        error: ((square / nreal - mean * mean).max(0.0) / (nreal - 1.0)).sqrt(),
+                    }
+                })
+                .collect();
+            combination.add(&results);
+            iterations.push(VegasIteration {
+                neval: n,
+                results,
+                chi2_dof: combination.chi2_dof(),
+            });
+            if !self.frozen {
+                grid.refine(&weights, self.alpha);
+            }
+
+            if niter.is_none() && (npoints >= self.mineval)
+                && meets_tolerance(&combination.results(), epsrel, epsabs) {
+                break true;
+            }
+        };
+
+        self.grid = Some(grid);
+        self.rng = Some(rng);
+        if iterations.is_empty() {
+            return Err(NativeError::VegasDidNotConverge(VegasIntegrationResults {
+                neval: fun.neval,
+                results: vec![IntegrationResult { value: 0.0, error: f64::INFINITY }; ncomp],
+                chi2_dof: vec![0.0; ncomp],
+                iterations,
+            }));
+        }
+        let results = VegasIntegrationResults {
+            neval: fun.neval,
+            results: combination.results(),
+            chi2_dof: combination.chi2_dof(),
+            iterations,
+        };
+        if converged {
+            Ok(results)
+        } else {
+            Err(NativeError::VegasDidNotConverge(results))
+        }
+    }
+}
+
+impl Integrator for Vegas {
+    type Success = VegasIntegrationResults;
+    type Failure = NativeError;
+    fn integrate<A, B, F: FnMut(A) -> B>(&mut self, fun: F, epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>
+        where A: IntegrandInput,
+              B: IntegrandOutput
+    {
+        self.run(fun, None, epsrel, epsabs)
+    }
+}
@@ -1,6 +1,10 @@
-use super::{Integrator, Real};
 #[cfg(feature = "cuba")]
-use super::cuba::{Cuhre, CubaError, Vegas};
+use super::{DynIntegrator, Integrator, Real, Real2, Real3};
+#[cfg(feature = "cuba")]
+use super::domain::{Axis, Ball, Domain, HyperRectangle, Simplex};
+#[cfg(feature = "cuba")]
+use super::cuba::{Cuhre, CubaError, CubaIntegrationResults, CubaSpin, Divonne,
+                  IntegrationRange, StateFileError, Suave, ThreadPool, Vegas};
 
 #[test]
 #[cfg(feature = "cuba")]
@@ -16,3 +20,317 @@ fn test_simple_integration() {
                             1e-4, 1e-12);
     assert!(b.is_ok());
 }
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_divonne() {
+    let mut divonne = Divonne::new().with_maxeval(1000000);
+
+    let a = divonne.integrate(|a: Real| a * a,
+                              1e-4, 1e-12);
+    assert_eq!(a, Err(CubaError::BadDim("divonne", 1)));
+
+    let b = divonne.integrate(|(a, b): Real2| a * b,
+                              1e-4, 1e-12)
+                   .expect("should converge");
+    assert!((b.results[0].value - 0.25).abs() < 1e-3);
+
+    let mut peaked = divonne.with_xgiven(vec![vec![0.5, 0.5]])
+                            .expect("valid point");
+    let c = peaked.integrate(|a: (Real, Real, Real)| a.0 + a.1 + a.2,
+                             1e-4, 1e-12);
+    assert_eq!(c, Err(CubaError::BadGivenDim(2, 3)));
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_vectorized_integration() {
+    let mut vegas = Vegas::default().with_maxeval(1000000).with_nvec(100);
+    let mut ncalls = 0;
+    let a = vegas.integrate_vectorized(|xs: &[Real]| {
+                                           ncalls += 1;
+                                           xs.iter().map(|&x| x * x).collect::<Vec<Real>>()
+                                       }, 1e-4, 1e-12)
+                 .expect("should converge");
+    assert!((a.results[0].value - 1.0 / 3.0).abs() < 1e-3);
+    assert!((ncalls as i64) < a.neval);
+
+    let mut cuhre = Cuhre::new(1000000).with_nvec(50);
+    let b = cuhre.integrate_vectorized(|xs: &[Real2]| {
+                                           xs.iter().map(|&(x, y)| vec![x, x * y])
+                                                    .collect::<Vec<Vec<Real>>>()
+                                       }, 1e-6, 1e-12)
+                 .expect("should converge");
+    assert!((b.results[0].value - 0.5).abs() < 1e-6);
+    assert!((b.results[1].value - 0.25).abs() < 1e-6);
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+#[should_panic(expected = "Vectorized integrand returned 1 outputs for")]
+fn test_vectorized_wrong_length() {
+    let mut vegas = Vegas::default().with_maxeval(1000000).with_nvec(100);
+    let _ = vegas.integrate_vectorized(|xs: &[Real]| vec![xs[0]], 1e-4, 1e-12);
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_vectorized_empty() {
+    let mut vegas = Vegas::default().with_maxeval(1000000).with_nvec(100);
+    assert_eq!(vegas.integrate_vectorized(|xs: &[[Real; 0]]| vec![1.0; xs.len()], 1e-4, 1e-12),
+               Err(CubaError::BadDim("vegas", 0)));
+    assert_eq!(vegas.integrate_vectorized(|xs: &[Real2]| vec![Vec::<Real>::new(); xs.len()],
+                                          1e-4, 1e-12),
+               Err(CubaError::BadComp("vegas", 0)));
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_parallel_deterministic() {
+    let integrand = |(x, y): Real2| (x * y).sin() + (3.0 * x).exp() * y;
+    let mut suave = Suave::new().with_maxeval(200000)
+                                .with_seed(12345)
+                                .with_nvec(512);
+    let serial = suave.integrate_parallel(&ThreadPool::new(1), integrand, 1e-4, 1e-12);
+    for &nthreads in [2, 3, 8].iter() {
+        let pool = ThreadPool::new(nthreads);
+        let parallel = suave.integrate_parallel(&pool, integrand, 1e-4, 1e-12);
+        assert_eq!(parallel, serial);
+    }
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+#[should_panic(expected = "bad point")]
+fn test_parallel_panic() {
+    let pool = ThreadPool::new(4);
+    let mut vegas = Vegas::default().with_maxeval(100000).with_nvec(1000);
+    let _ = vegas.integrate_parallel(&pool, |x: Real| {
+        if x > 0.9 {
+            panic!("bad point");
+        }
+        x
+    }, 1e-4, 1e-12);
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_thread_pool_map() {
+    let points = (0..1001).map(|i| i as Real).collect::<Vec<Real>>();
+    let expected = points.iter().map(|x| x.sqrt()).collect::<Vec<Real>>();
+    for &nthreads in [0, 1, 2, 7].iter() {
+        let pool = ThreadPool::new(nthreads);
+        assert_eq!(pool.map(&|x: Real| x.sqrt(), &points[..]), expected);
+        assert_eq!(pool.map(&|x: Real| x.sqrt(), &points[..3]), &expected[..3]);
+    }
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_vegas_statefile() {
+    use std::{env, fs, process};
+
+    let path = env::temp_dir().join(format!("integrators-vegas-{}.state", process::id()));
+    let _ = fs::remove_file(&path);
+    assert!(Vegas::new().with_trained_statefile(&path).is_err());
+
+    let mut vegas = Vegas::new().with_maxeval(100000)
+                                .with_statefile(&path).expect("valid path")
+                                .with_retain_statefile(true);
+    assert_eq!(vegas.statefile(), Some(path.as_path()));
+    vegas.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12)
+         .expect("should converge");
+    assert!(path.exists());
+
+    let mut resumed = Vegas::new().with_maxeval(100000)
+                                  .with_trained_statefile(&path)
+                                  .expect("state file was retained")
+                                  .with_grid_only(true);
+    let res = resumed.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12)
+                     .expect("should converge");
+    assert!((res.results[0].value - 0.25).abs() < 1e-3);
+
+    // The state of a 2-dimensional integrand cannot be reused for a
+    // 3-dimensional one
+    assert_eq!(resumed.integrate(|(x, y, z): Real3| x * y * z, 1e-3, 1e-12),
+               Err(CubaError::BadStateFile(StateFileError::Mismatch(2, 1))));
+    assert_eq!(resumed.integrate(|(x, y): Real2| (x, y), 1e-3, 1e-12),
+               Err(CubaError::BadStateFile(StateFileError::Mismatch(2, 1))));
+
+    fs::write(&path, b"CUBA").expect("can write to temp dir");
+    assert_eq!(resumed.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12),
+               Err(CubaError::BadStateFile(StateFileError::Truncated)));
+    fs::write(&path, b"not a vegas state file").expect("can write to temp dir");
+    assert_eq!(resumed.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12),
+               Err(CubaError::BadStateFile(StateFileError::NotVegas)));
+    fs::remove_file(&path).expect("state file was written");
+}
+
+#[test]
+#[cfg(all(feature = "cuba", unix))]
+fn test_vegas_statefile_non_utf8() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    let path = Path::new(OsStr::from_bytes(b"/tmp/integrators-\xff.state"));
+    let vegas = Vegas::new().with_statefile(path).expect("valid unix path");
+    assert_eq!(vegas.statefile(), Some(path));
+    assert!(Vegas::new().with_statefile("/tmp/nul\0.state").is_none());
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_vegas_rng_keeps_flags() {
+    use std::{env, fs, process};
+    use super::cuba::RandomNumberSource;
+
+    // Choosing the random number source must not clear the other flags
+    let path = env::temp_dir().join(format!("integrators-vegas-rng-{}.state", process::id()));
+    let _ = fs::remove_file(&path);
+    let mut vegas = Vegas::new().with_maxeval(100000)
+                                .with_statefile(&path).expect("valid path")
+                                .with_retain_statefile(true)
+                                .with_rng(RandomNumberSource::MersenneTwister);
+    vegas.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12)
+         .expect("should converge");
+    assert!(path.exists(), "state file was not retained");
+    fs::remove_file(&path).expect("state file was retained");
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_vegas_gridno() {
+    assert!(Vegas::new().with_gridno(0).is_some());
+    assert!(Vegas::new().with_gridno(10).is_some());
+    assert!(Vegas::new().with_gridno(-10).is_some());
+    assert!(Vegas::new().with_gridno(11).is_none());
+    assert!(Vegas::new().with_gridno(-11).is_none());
+
+    let mut vegas = Vegas::new().with_maxeval(100000)
+                                .with_gridno(1).expect("valid gridno");
+    for _ in 0..3 {
+        let res = vegas.integrate(|(x, y): Real2| x * y, 1e-3, 1e-12)
+                       .expect("should converge");
+        assert!((res.results[0].value - 0.25).abs() < 1e-3);
+    }
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_spin() {
+    let mut cuhre = Cuhre::new(100000).with_spin(CubaSpin::new());
+    for _ in 0..3 {
+        let res = cuhre.integrate(|(x, y): Real2| x * y, 1e-6, 1e-12)
+                       .expect("should converge");
+        assert!((res.results[0].value - 0.25).abs() < 1e-6);
+    }
+
+    // No workers are started, so the handle comes back empty.
+    let mut spin = cuhre.take_spin().expect("was given a handle");
+    assert!(!spin.is_running());
+    assert!(cuhre.take_spin().is_none());
+    spin.wait();
+
+    let mut vegas = Vegas::new().with_maxeval(100000)
+                                .with_spin(spin.clone());
+    vegas.integrate(|x: Real| x, 1e-3, 1e-12)
+         .expect("should converge");
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_ranges() {
+    let ranges = || vec![IntegrationRange::new(0.0, 2.0), IntegrationRange::new(1.0, 3.0)];
+    // The integral of x * y over [0, 2] x [1, 3], and the area
+    let fun = |(x, y): Real2| {
+        assert!((x >= 0.0) & (x <= 2.0) & (y >= 1.0) & (y <= 3.0));
+        (x * y, 1.0)
+    };
+    let check = |res: Result<CubaIntegrationResults, CubaError>| {
+        let res = res.expect("should converge");
+        assert!((res.results[0].value - 8.0).abs() < 1e-2, "{:?}", res);
+        assert!((res.results[1].value - 4.0).abs() < 1e-2, "{:?}", res);
+    };
+
+    check(Cuhre::new(1000000).with_ranges(ranges()).integrate(fun, 1e-6, 1e-12));
+    check(Vegas::new().with_maxeval(1000000).with_ranges(ranges()).integrate(fun, 1e-3, 1e-12));
+    check(Suave::new().with_maxeval(1000000).with_ranges(ranges()).integrate(fun, 1e-3, 1e-12));
+    check(Divonne::new().with_maxeval(1000000).with_ranges(ranges()).integrate(fun, 1e-3, 1e-12));
+    check(Cuhre::new(1000000).with_ranges(ranges())
+                             .with_nvec(100)
+                             .integrate_vectorized(|points: &[Real2]| {
+                                 points.iter().map(|&p| fun(p)).collect::<Vec<_>>()
+                             }, 1e-6, 1e-12));
+
+    let mut cuhre = Cuhre::new(1000000).with_ranges(ranges());
+    assert_eq!(cuhre.integrate(|(x, y, z): (Real, Real, Real)| x * y * z, 1e-6, 1e-12),
+               Err(CubaError::BadRangeDim(2, 3)));
+    assert!(cuhre.without_ranges().ranges().is_none());
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_domains() {
+    use std::f64::consts::PI;
+
+    // The volume of a ball, the second moment of a Gaussian over all of
+    // space, and the area of a triangle
+    let ball = Ball::new((0.0, 0.0, 0.0), 2.0);
+    let res = ball.integrate(&mut Cuhre::new(1000000), |_: Real3| 1.0, 1e-6, 1e-12).unwrap();
+    assert!((res.results[0].value - 32.0 / 3.0 * PI).abs() < 1e-4, "{:?}", res);
+
+    let space = HyperRectangle::new(vec![Axis::Whole; 3]).unwrap();
+    let res = space.integrate(&mut Vegas::new().with_maxeval(1000000),
+                              |(x, y, z): Real3| {
+                                  z * z * (-(x * x + y * y + z * z) / 2.0).exp()
+                              }, 1e-3, 1e-12)
+                   .unwrap();
+    let exp = (2.0 * PI).powf(1.5);
+    assert!((res.results[0].value - exp).abs() < 1e-2 * exp, "{:?}", res);
+
+    let triangle = Simplex::new(&[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]).unwrap();
+    let res = triangle.integrate(&mut Suave::new().with_maxeval(1000000),
+                                 |_: Real2| 1.0, 1e-3, 1e-12)
+                      .unwrap();
+    assert!((res.results[0].value - 0.5).abs() < 1e-2, "{:?}", res);
+}
+
+#[test]
+#[cfg(feature = "cuba")]
+fn test_integrate_dyn() {
+    use std::panic;
+
+    // The mean of the sum of the coordinates over the unit hypercube, and
+    // its volume, for dimensions only known at runtime
+    for ndim in 2..6 {
+        let mut fun = |x: &[Real], f: &mut [Real]| {
+            assert_eq!(x.len(), ndim);
+            f[0] = x.iter().sum();
+            f[1] = 1.0;
+        };
+        let check = |res: Result<CubaIntegrationResults, CubaError>| {
+            let res = res.expect("should converge");
+            assert!((res.results[0].value - ndim as Real / 2.0).abs() < 1e-2, "{:?}", res);
+            assert!((res.results[1].value - 1.0).abs() < 1e-2, "{:?}", res);
+        };
+        check(Cuhre::new(1000000).integrate_dyn(ndim, 2, &mut fun, 1e-6, 1e-12));
+        check(Vegas::new().with_maxeval(1000000).integrate_dyn(ndim, 2, &mut fun, 1e-3, 1e-12));
+        check(Suave::new().with_maxeval(1000000).integrate_dyn(ndim, 2, &mut fun, 1e-3, 1e-12));
+    }
+
+    let ranges = vec![IntegrationRange::new(0.0, 2.0), IntegrationRange::new(1.0, 3.0)];
+    let mut cuhre = Cuhre::new(1000000).with_ranges(ranges);
+    let res = cuhre.integrate_dyn(2, 1, &mut |x, f| f[0] = x[0] * x[1], 1e-6, 1e-12)
+                   .expect("should converge");
+    assert!((res.results[0].value - 8.0).abs() < 1e-4, "{:?}", res);
+    assert_eq!(cuhre.integrate_dyn(3, 1, &mut |_, f| f[0] = 1.0, 1e-6, 1e-12),
+               Err(CubaError::BadRangeDim(2, 3)));
+    assert_eq!(Cuhre::new(1000).integrate_dyn(1, 1, &mut |_, f| f[0] = 1.0, 1e-6, 1e-12),
+               Err(CubaError::BadDim("cuhre", 1)));
+
+    let panicked = panic::catch_unwind(|| {
+        Vegas::new().integrate_dyn(2, 1, &mut |x, _| panic!("at {:?}", x), 1e-3, 1e-12)
+    });
+    assert!(panicked.is_err());
+}
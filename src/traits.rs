@@ -24,6 +24,37 @@ pub trait Integrator {
               B: IntegrandOutput;
 }
 
+/// Integrators which can also integrate integrands whose numbers of inputs
+/// and outputs are only known at runtime, such as when they are read from a
+/// configuration file. The integrand is passed as a trait object, so the
+/// integrator is compiled once, rather than once per dimension.
+pub trait DynIntegrator: Integrator {
+    /// Integrates `fun` as `integrate` does. `fun` is called with the `ndim`
+    /// coordinates of each point, and must write its `ncomp` outputs to its
+    /// second argument. If the integrator does not support `ndim` or `ncomp`,
+    /// an `Err(Self::Failure)` is returned.
+    ///
+    /// ```
+    /// use integrators::{DynIntegrator, Real};
+    /// # #[cfg(feature = "cuba")] {
+    /// use integrators::cuba::Cuhre;
+    ///
+    /// // The dimension could just as well come from user input
+    /// let ndim = 3;
+    /// let mut fun = |x: &[Real], f: &mut [Real]| {
+    ///     f[0] = x.iter().sum();
+    ///     f[1] = x.iter().product();
+    /// };
+    /// let res = Cuhre::new(100000).integrate_dyn(ndim, 2, &mut fun, 1e-8, 1e-12)
+    ///                             .unwrap();
+    /// assert!((res.results[0].value - 1.5).abs() < 1e-6);
+    /// assert!((res.results[1].value - 0.125).abs() < 1e-6);
+    /// # }
+    /// ```
+    fn integrate_dyn(&mut self, ndim: usize, ncomp: usize, fun: &mut dyn FnMut(&[Real], &mut [Real]),
+                     epsrel: Real, epsabs: Real) -> Result<Self::Success, Self::Failure>;
+}
+
 pub trait IntegrandInput {
     fn input_size() -> usize;
     fn from_args(&[Real]) -> Self;
@@ -137,7 +168,7 @@ impl_integrand_traits!((Real, Real, Real, Real, Real, Real, Real), 7,
                            args[5] = this.5;
                            args[6] = this.6;
                        });
-impl_integrand_traits!((Real, Real, Real, Real, Real, Real, Real, Real), 7,
+impl_integrand_traits!((Real, Real, Real, Real, Real, Real, Real, Real), 8,
                        |args: &[Real]| { (args[0], args[1], args[2], args[3], args[4], args[5], args[6], args[7]) },
                        |this: &(Real, Real, Real, Real, Real, Real, Real, Real), args: &mut [Real]| {
                            args[0] = this.0;
@@ -150,6 +181,29 @@ impl_integrand_traits!((Real, Real, Real, Real, Real, Real, Real, Real), 7,
                            args[7] = this.7;
                        });
 
+impl<const N: usize> IntegrandInput for [Real; N] {
+    fn input_size() -> usize {
+        N
+    }
+
+    fn from_args(args: &[Real]) -> Self {
+        assert!(args.len() == Self::input_size());
+        let mut array = [0.0; N];
+        array.copy_from_slice(args);
+        array
+    }
+}
+
+impl<const N: usize> IntegrandOutput for [Real; N] {
+    fn output_size(&self) -> usize {
+        N
+    }
+
+    fn into_args(&self, args: &mut [Real]) {
+        assert!(args.len() == self.output_size());
+        args.copy_from_slice(self);
+    }
+}
 
 #[cfg(test)]
 mod test_traits {
@@ -200,4 +254,33 @@ mod test_traits {
         let mut args: [Real; 10] = [0.0; 10];
         v.into_args(&mut args);
     }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(<(Real, Real, Real, Real, Real, Real, Real)>::input_size(), 7);
+        assert_eq!(<(Real, Real, Real, Real, Real, Real, Real, Real)>::input_size(), 8);
+        let args: Vec<Real> = (0..8).map(|i| i as Real).collect();
+        let e = <(Real, Real, Real, Real, Real, Real, Real, Real)>::from_args(&args);
+        assert_eq!(e.output_size(), 8);
+        let mut out = [0.0; 8];
+        e.into_args(&mut out);
+        assert_eq!(&out[..], &args[..]);
+    }
+
+    #[test]
+    fn test_array_traits() {
+        let args: Vec<Real> = (0..10).map(|i| i as Real).collect();
+        assert_eq!(<[Real; 10]>::input_size(), 10);
+        let a = <[Real; 10]>::from_args(&args);
+        assert_eq!(&a[..], &args[..]);
+        let mut out = [0.0; 10];
+        [1.0, 2.0, 3.0].into_args(&mut out[..3]);
+        assert_eq!(&out[..3], &[1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_array_failure() {
+        let _a = <[Real; 3]>::from_args(&[1.0, 2.0]);
+    }
 }